@@ -0,0 +1,462 @@
+use sha2::{Digest, Sha256};
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use super::invoice::{
+    BindleSpec, Condition, FeatureMap, Group, Invoice, Label, Parcel, BINDLE_VERSION_1,
+};
+use crate::hippofacts::{ExternalRef, Handler, HandlerModule, HippoFacts};
+
+const WASM_MEDIA_TYPE: &str = "application/wasm";
+
+pub const INVOICE_FILE_NAME: &str = "invoice.toml";
+pub const PARCELS_DIR_NAME: &str = "parcels";
+
+/// Labels of parcels in other bindles, keyed by bindle ID and parcel name
+pub type ExternalParcels = BTreeMap<(String, String), Label>;
+
+pub struct Build {
+    pub invoice: Invoice,
+    /// Maps each parcel's SHA-256 digest to the file it was read from. Parcels
+    /// from external bindles are already on the server and have no source.
+    pub sources: BTreeMap<String, PathBuf>,
+}
+
+impl Build {
+    /// Writes the invoice and parcels to `dir` as `invoice.toml` and `parcels/<sha256>.dat`
+    pub fn write_to(&self, dir: &Path) -> anyhow::Result<()> {
+        if dir.exists() && dir.read_dir()?.next().is_some() {
+            anyhow::bail!("{} already exists and is not empty", dir.display());
+        }
+        let parcels_dir = dir.join(PARCELS_DIR_NAME);
+        std::fs::create_dir_all(&parcels_dir)?;
+        std::fs::write(dir.join(INVOICE_FILE_NAME), self.invoice.to_toml()?)?;
+        for (sha256, source) in &self.sources {
+            std::fs::copy(source, parcels_dir.join(format!("{}.dat", sha256)))
+                .map_err(|e| anyhow::anyhow!("cannot copy {}: {}", source.display(), e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Builds the invoice for the bindle described by `facts`, reading handler
+/// modules and files relative to `source_dir`. The result depends only on
+/// the manifest and the file contents, so the same inputs always produce
+/// the same invoice.
+pub fn build(
+    facts: &HippoFacts,
+    source_dir: &Path,
+    externals: &ExternalParcels,
+) -> anyhow::Result<Build> {
+    let mut builder = Builder {
+        source_dir,
+        parcels: BTreeMap::new(),
+        order: vec![],
+        modules: BTreeMap::new(),
+        sources: BTreeMap::new(),
+    };
+    let mut groups: Vec<Group> = vec![];
+
+    for handler in &facts.handlers {
+        let mut module = match &handler.module {
+            HandlerModule::File(name) => builder.read_label(name, Some(WASM_MEDIA_TYPE))?,
+            HandlerModule::External(ext) => external_label(ext, externals)?,
+        };
+        module.feature = Some(wagi_feature(&[
+            ("route", &handler.route),
+            ("file", "false"),
+        ]));
+
+        let files = expand_files(source_dir, handler)?;
+        let group_name = format!("{}-files", module.name);
+        let requires = if files.is_empty() {
+            None
+        } else {
+            if groups.iter().any(|g| g.name == group_name) {
+                anyhow::bail!(
+                    "handler for route '{}' reuses module {}, which already has a file group",
+                    handler.route,
+                    module.name
+                );
+            }
+            groups.push(Group {
+                name: group_name.clone(),
+                required: Some(false),
+                satisfied_by: Some("allOf".to_owned()),
+            });
+            Some(vec![group_name.clone()])
+        };
+        builder.add_module(
+            &handler.route,
+            module,
+            Condition {
+                requires,
+                ..Default::default()
+            },
+        )?;
+
+        for file in files {
+            let mut label = builder.read_label(&file, None)?;
+            label.feature = Some(wagi_feature(&[("file", "true")]));
+            builder.add_file(&handler.route, label, &group_name)?;
+        }
+    }
+
+    let invoice = Invoice {
+        bindle_version: BINDLE_VERSION_1.to_owned(),
+        bindle: BindleSpec {
+            name: facts.bindle.name.clone(),
+            version: facts.bindle.version.to_string(),
+            description: facts.bindle.description.clone(),
+            authors: facts.bindle.authors.clone(),
+        },
+        annotations: facts.annotations.clone(),
+        parcel: none_if_empty(builder.parcels()),
+        group: none_if_empty(groups),
+    };
+
+    Ok(Build {
+        invoice,
+        sources: builder.sources,
+    })
+}
+
+/// Identifies a parcel by its SHA-256 digest and name, so files with the same
+/// content under different names each get a parcel
+type ParcelKey = (String, String);
+
+struct Builder<'a> {
+    source_dir: &'a Path,
+    parcels: BTreeMap<ParcelKey, Parcel>,
+    /// Parcel keys in the order they were first added
+    order: Vec<ParcelKey>,
+    /// The route each handler module parcel serves
+    modules: BTreeMap<ParcelKey, String>,
+    sources: BTreeMap<String, PathBuf>,
+}
+
+impl<'a> Builder<'a> {
+    fn read_label(&mut self, name: &str, media_type: Option<&str>) -> anyhow::Result<Label> {
+        let path = self.source_dir.join(name);
+        let content = std::fs::read(&path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+        let sha256 = format!("{:x}", Sha256::digest(&content));
+        let media_type = match media_type {
+            Some(m) => m.to_owned(),
+            None => mime_guess::from_path(&path)
+                .first_or_octet_stream()
+                .to_string(),
+        };
+        self.sources.insert(sha256.clone(), path);
+        Ok(Label {
+            sha256,
+            media_type,
+            name: name.to_owned(),
+            size: content.len() as u64,
+            feature: None,
+        })
+    }
+
+    fn add_module(
+        &mut self,
+        route: &str,
+        label: Label,
+        conditions: Condition,
+    ) -> anyhow::Result<()> {
+        let key = (label.sha256.clone(), label.name.clone());
+        match self.parcels.entry(key.clone()) {
+            Entry::Occupied(_) => match self.modules.get(&key) {
+                Some(other_route) => anyhow::bail!(
+                    "handlers for routes '{}' and '{}' both use module {}; a module parcel can only serve one route, so copy the module under another name",
+                    other_route,
+                    route,
+                    label.name
+                ),
+                None => anyhow::bail!(
+                    "handler for route '{}' uses module {}, which is already in the bindle as a file; a parcel cannot be both a module and a file",
+                    route,
+                    label.name
+                ),
+            },
+            Entry::Vacant(v) => {
+                self.order.push(key.clone());
+                self.modules.insert(key, route.to_owned());
+                v.insert(Parcel {
+                    label,
+                    conditions: Some(conditions).filter(|c| c.requires.is_some()),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn add_file(&mut self, route: &str, label: Label, group_name: &str) -> anyhow::Result<()> {
+        let key = (label.sha256.clone(), label.name.clone());
+        if let Some(module_route) = self.modules.get(&key) {
+            anyhow::bail!(
+                "file {} for route '{}' is the module for route '{}'; a parcel cannot be both a module and a file",
+                label.name,
+                route,
+                module_route
+            );
+        }
+        match self.parcels.entry(key.clone()) {
+            // a file is stored once, as a member of every group that needs it
+            Entry::Occupied(mut o) => {
+                let conditions = o.get_mut().conditions.get_or_insert_with(Default::default);
+                let member_of = conditions.member_of.get_or_insert_with(Vec::new);
+                if !member_of.iter().any(|g| g == group_name) {
+                    member_of.push(group_name.to_owned());
+                }
+            }
+            Entry::Vacant(v) => {
+                self.order.push(key);
+                v.insert(Parcel {
+                    label,
+                    conditions: Some(Condition {
+                        member_of: Some(vec![group_name.to_owned()]),
+                        ..Default::default()
+                    }),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn parcels(&mut self) -> Vec<Parcel> {
+        std::mem::take(&mut self.order)
+            .iter()
+            .filter_map(|key| self.parcels.remove(key))
+            .collect()
+    }
+}
+
+/// Expands the handler's file patterns into a sorted, de-duplicated list of
+/// paths relative to `source_dir`, using `/` as the separator on every platform
+fn expand_files(source_dir: &Path, handler: &Handler) -> anyhow::Result<BTreeSet<String>> {
+    let mut files = BTreeSet::new();
+    let base = glob::Pattern::escape(&source_dir.to_string_lossy());
+    for pattern in &handler.files {
+        let full_pattern = Path::new(&base).join(pattern);
+        let mut matched = false;
+        for entry in glob::glob(&full_pattern.to_string_lossy())? {
+            let path = entry?;
+            if !path.is_file() {
+                continue;
+            }
+            let relative = path.strip_prefix(source_dir).unwrap_or(&path);
+            let name = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            files.insert(name);
+            matched = true;
+        }
+        if !matched {
+            anyhow::bail!(
+                "file pattern '{}' for route '{}' does not match any files",
+                pattern,
+                handler.route
+            );
+        }
+    }
+    Ok(files)
+}
+
+fn external_label(ext: &ExternalRef, externals: &ExternalParcels) -> anyhow::Result<Label> {
+    externals
+        .get(&(ext.bindle_id.clone(), ext.handler_id.clone()))
+        .cloned()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "external handler {} in bindle {} has not been resolved",
+                ext.handler_id,
+                ext.bindle_id
+            )
+        })
+}
+
+fn wagi_feature(values: &[(&str, &str)]) -> FeatureMap {
+    let wagi = values
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    let mut feature = FeatureMap::new();
+    feature.insert("wagi".to_owned(), wagi);
+    feature
+}
+
+fn none_if_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    if items.is_empty() {
+        None
+    } else {
+        Some(items)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Writes `files` to a new temporary directory and parses `facts`
+    pub(crate) fn source_dir(
+        facts: &str,
+        files: &[(&str, &str)],
+    ) -> (tempfile::TempDir, HippoFacts) {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, content) in files {
+            let path = dir.path().join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        }
+        (dir, HippoFacts::parse(facts).unwrap())
+    }
+
+    const TWO_HANDLERS: &str = r#"
+[bindle]
+name = "weather"
+version = "1.0.0"
+
+[[handler]]
+route = "/"
+name = "site.wasm"
+files = ["static/*"]
+
+[[handler]]
+route = "/api"
+name = "api.wasm"
+files = ["data/*.json"]
+"#;
+
+    fn two_handler_files() -> Vec<(&'static str, &'static str)> {
+        vec![
+            ("site.wasm", "site module"),
+            ("api.wasm", "api module"),
+            ("static/index.html", "<h1>weather</h1>"),
+            ("static/style.css", "h1 {}"),
+            ("data/cities.json", "[]"),
+        ]
+    }
+
+    fn parcel<'a>(build: &'a Build, name: &str) -> &'a Parcel {
+        build
+            .invoice
+            .parcel
+            .iter()
+            .flatten()
+            .find(|p| p.label.name == name)
+            .unwrap()
+    }
+
+    #[test]
+    fn the_same_inputs_build_the_same_invoice() {
+        let mut files = two_handler_files();
+        let (dir, facts) = source_dir(TWO_HANDLERS, &files);
+        let first = build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+        let second = build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+        assert_eq!(
+            first.invoice.to_toml().unwrap(),
+            second.invoice.to_toml().unwrap()
+        );
+
+        // neither the directory nor the order the files were written in matters
+        files.reverse();
+        let (other_dir, facts) = source_dir(TWO_HANDLERS, &files);
+        let other = build(&facts, other_dir.path(), &ExternalParcels::new()).unwrap();
+        assert_eq!(
+            first.invoice.to_toml().unwrap(),
+            other.invoice.to_toml().unwrap()
+        );
+    }
+
+    #[test]
+    fn identical_files_get_a_parcel_each_with_one_source() {
+        let (dir, facts) = source_dir(
+            TWO_HANDLERS,
+            &[
+                ("site.wasm", "site module"),
+                ("api.wasm", "api module"),
+                ("static/a.txt", "same"),
+                ("static/b.txt", "same"),
+                ("data/cities.json", "[]"),
+            ],
+        );
+        let build = build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+        let a = parcel(&build, "static/a.txt");
+        let b = parcel(&build, "static/b.txt");
+        assert_eq!(a.label.sha256, b.label.sha256);
+        assert_eq!(build.invoice.parcel.as_ref().unwrap().len(), 5);
+        assert_eq!(build.sources.len(), 4);
+    }
+
+    #[test]
+    fn each_handlers_files_are_in_its_own_group() {
+        let (dir, facts) = source_dir(TWO_HANDLERS, &two_handler_files());
+        let build = build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+
+        let groups: Vec<_> = build
+            .invoice
+            .group
+            .iter()
+            .flatten()
+            .map(|g| g.name.as_str())
+            .collect();
+        assert_eq!(groups, vec!["site.wasm-files", "api.wasm-files"]);
+
+        let requires = |name| {
+            parcel(&build, name)
+                .conditions
+                .as_ref()
+                .unwrap()
+                .requires
+                .clone()
+        };
+        let member_of = |name| {
+            parcel(&build, name)
+                .conditions
+                .as_ref()
+                .unwrap()
+                .member_of
+                .clone()
+        };
+        assert_eq!(
+            requires("site.wasm"),
+            Some(vec!["site.wasm-files".to_owned()])
+        );
+        assert_eq!(
+            requires("api.wasm"),
+            Some(vec!["api.wasm-files".to_owned()])
+        );
+        assert_eq!(
+            member_of("static/index.html"),
+            Some(vec!["site.wasm-files".to_owned()])
+        );
+        assert_eq!(
+            member_of("static/style.css"),
+            Some(vec!["site.wasm-files".to_owned()])
+        );
+        assert_eq!(
+            member_of("data/cities.json"),
+            Some(vec!["api.wasm-files".to_owned()])
+        );
+    }
+
+    #[test]
+    fn a_file_shared_by_two_handlers_is_in_both_groups() {
+        let (dir, facts) = source_dir(
+            &TWO_HANDLERS.replace("data/*.json", "static/style.css"),
+            &two_handler_files(),
+        );
+        let build = build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+        let style = parcel(&build, "static/style.css");
+        assert_eq!(
+            style.conditions.as_ref().unwrap().member_of,
+            Some(vec![
+                "site.wasm-files".to_owned(),
+                "api.wasm-files".to_owned()
+            ])
+        );
+    }
+}
@@ -0,0 +1,499 @@
+use futures::{stream, StreamExt};
+use reqwest::{header, Body, StatusCode};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::path::Path;
+
+use super::builder::{Build, ExternalParcels};
+use super::invoice::{Invoice, Label};
+use crate::client::ConnectionInfo;
+use crate::hippofacts::{HandlerModule, HippoFacts, HIPPOFACTS_FILE_NAME};
+
+const TOML_MIME_TYPE: &str = "application/toml";
+const MAX_CONCURRENT_UPLOADS: usize = 4;
+
+pub struct Client {
+    base_url: String,
+    client: reqwest::Client,
+}
+
+impl Client {
+    pub fn new(conn_info: ConnectionInfo) -> Self {
+        let mut headers = header::HeaderMap::new();
+        headers.insert(header::ACCEPT, TOML_MIME_TYPE.parse().unwrap());
+        if let Some(key) = conn_info.api_key {
+            headers.insert(
+                header::AUTHORIZATION,
+                format!("Bearer {}", key).parse().unwrap(),
+            );
+        }
+
+        let base_url = match conn_info.url.strip_suffix('/') {
+            Some(s) => s.to_owned(),
+            None => conn_info.url,
+        };
+        let client = reqwest::Client::builder()
+            .user_agent(format!(
+                "{}/{}",
+                env!("CARGO_PKG_NAME"),
+                env!("CARGO_PKG_VERSION")
+            ))
+            .danger_accept_invalid_certs(conn_info.danger_accept_invalid_certs)
+            .default_headers(headers)
+            .build()
+            .unwrap();
+
+        Self { base_url, client }
+    }
+
+    /// Creates the invoice and uploads the parcels the server reports as
+    /// missing, several at a time. Parcels the server already stores, such
+    /// as unchanged assets from an earlier version, are not sent again. If
+    /// the bindle already exists with the same invoice, only what an earlier,
+    /// interrupted push did not upload is sent.
+    ///
+    /// The summary counts every parcel in the invoice, although parcels that
+    /// share content are only uploaded once.
+    pub async fn push(&self, build: &Build) -> anyhow::Result<PushSummary> {
+        let bindle_id = build.invoice.bindle_id();
+        let missing = match self.create_invoice(&build.invoice).await? {
+            Some(missing) => missing,
+            None => {
+                let existing = self.get_invoice(&bindle_id).await?;
+                if existing != build.invoice {
+                    anyhow::bail!(
+                        "bindle {} already exists with different contents; change the version in {} to push these changes",
+                        bindle_id,
+                        HIPPOFACTS_FILE_NAME
+                    );
+                }
+                self.get_missing_parcels(&bindle_id).await?
+            }
+        };
+
+        let mut summary = PushSummary::default();
+        for parcel in build.invoice.parcel.iter().flatten() {
+            if missing.iter().any(|l| l.sha256 == parcel.label.sha256) {
+                summary.uploaded += 1;
+                summary.uploaded_bytes += parcel.label.size;
+            } else {
+                summary.skipped += 1;
+                summary.skipped_bytes += parcel.label.size;
+            }
+        }
+
+        // parcels with the same content under different names are uploaded once
+        let mut uploaded = BTreeSet::new();
+        let missing: Vec<_> = missing
+            .into_iter()
+            .filter(|l| uploaded.insert(l.sha256.clone()))
+            .collect();
+        let uploads = missing.iter().map(|label| {
+            let bindle_id = &bindle_id;
+            async move {
+                let path = build.sources.get(&label.sha256).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "server reported parcel {} ({}) as missing, but it is not part of this build",
+                        label.name,
+                        label.sha256
+                    )
+                })?;
+                self.upload_parcel(bindle_id, &label.sha256, path, label.size)
+                    .await?;
+                log::info!("uploaded {} ({} bytes)", label.name, label.size);
+                Ok::<_, anyhow::Error>(())
+            }
+        });
+        let mut uploads = stream::iter(uploads).buffer_unordered(MAX_CONCURRENT_UPLOADS);
+        while let Some(result) = uploads.next().await {
+            result?;
+        }
+
+        Ok(summary)
+    }
+
+    pub async fn get_invoice(&self, bindle_id: &str) -> anyhow::Result<Invoice> {
+        let response = self
+            .client
+            .get(format!("{}/_i/{}", self.base_url, bindle_id))
+            .send()
+            .await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            anyhow::bail!("bindle {} does not exist", bindle_id);
+        }
+        let text = check_response(response).await?.text().await?;
+        toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid invoice for bindle {}: {}", bindle_id, e))
+    }
+
+    /// Looks up the parcels that the handlers in `facts` reference in other bindles
+    pub async fn resolve_externals(&self, facts: &HippoFacts) -> anyhow::Result<ExternalParcels> {
+        let mut externals = ExternalParcels::new();
+        for handler in &facts.handlers {
+            let ext = match &handler.module {
+                HandlerModule::External(ext) => ext,
+                HandlerModule::File(_) => continue,
+            };
+            let key = (ext.bindle_id.clone(), ext.handler_id.clone());
+            if externals.contains_key(&key) {
+                continue;
+            }
+            let invoice = self.get_invoice(&ext.bindle_id).await?;
+            let mut label = invoice
+                .parcel
+                .unwrap_or_default()
+                .into_iter()
+                .map(|p| p.label)
+                .find(|l| l.name == ext.handler_id)
+                .ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "bindle {} has no parcel named {}",
+                        ext.bindle_id,
+                        ext.handler_id
+                    )
+                })?;
+            label.feature = None;
+            externals.insert(key, label);
+        }
+        Ok(externals)
+    }
+
+    /// Creates the invoice, returning the parcels the server does not have
+    /// yet, or `None` if the invoice already exists
+    pub async fn create_invoice(&self, invoice: &Invoice) -> anyhow::Result<Option<Vec<Label>>> {
+        let response = self
+            .client
+            .post(format!("{}/_i", self.base_url))
+            .header(header::CONTENT_TYPE, TOML_MIME_TYPE)
+            .body(invoice.to_toml()?)
+            .send()
+            .await?;
+        if response.status() == StatusCode::CONFLICT {
+            return Ok(None);
+        }
+        let text = check_response(response).await?.text().await?;
+        let created: InvoiceCreateResponse = toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid response from Bindle server: {}", e))?;
+        Ok(Some(created.missing.unwrap_or_default()))
+    }
+
+    pub async fn get_missing_parcels(&self, bindle_id: &str) -> anyhow::Result<Vec<Label>> {
+        let response = self
+            .client
+            .get(format!("{}/_r/missing/{}", self.base_url, bindle_id))
+            .send()
+            .await?;
+        let text = check_response(response).await?.text().await?;
+        let missing: MissingParcelsResponse = toml::from_str(&text)
+            .map_err(|e| anyhow::anyhow!("invalid response from Bindle server: {}", e))?;
+        Ok(missing.missing)
+    }
+
+    /// Streams the parcel from disk rather than reading it into memory first
+    pub async fn upload_parcel(
+        &self,
+        bindle_id: &str,
+        sha256: &str,
+        path: &Path,
+        size: u64,
+    ) -> anyhow::Result<()> {
+        let file = tokio::fs::File::open(path)
+            .await
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+        let response = self
+            .client
+            .post(format!("{}/_i/{}@{}", self.base_url, bindle_id, sha256))
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(header::CONTENT_LENGTH, size)
+            .body(Body::from(file))
+            .send()
+            .await?;
+        match response.status() {
+            // the parcel is already stored on the server
+            StatusCode::CONFLICT => Ok(()),
+            _ => check_response(response).await.map(|_| ()),
+        }
+    }
+}
+
+/// What a push sent, counting every parcel in the invoice once
+#[derive(Default)]
+pub struct PushSummary {
+    /// Parcels the server was missing, including any that share their content
+    /// with another parcel and so were sent with it
+    pub uploaded: usize,
+    pub uploaded_bytes: u64,
+    pub skipped: usize,
+    pub skipped_bytes: u64,
+}
+
+impl fmt::Display for PushSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "uploaded {} parcel(s) ({} bytes), {} already on the server ({} bytes)",
+            self.uploaded, self.uploaded_bytes, self.skipped, self.skipped_bytes
+        )
+    }
+}
+
+#[derive(Deserialize)]
+struct InvoiceCreateResponse {
+    missing: Option<Vec<Label>>,
+}
+
+#[derive(Deserialize)]
+struct MissingParcelsResponse {
+    missing: Vec<Label>,
+}
+
+async fn check_response(response: reqwest::Response) -> anyhow::Result<reqwest::Response> {
+    if response.status().is_success() {
+        return Ok(response);
+    }
+    let status = response.status();
+    let content = response.text().await.unwrap_or_default();
+    if content.is_empty() {
+        anyhow::bail!("Bindle server returned {}", status)
+    }
+    anyhow::bail!("Bindle server returned {}: {}", status, content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindle::builder::{self, tests::source_dir};
+    use mockito::{Matcher, Server};
+
+    const FACTS: &str = r#"
+[bindle]
+name = "weather"
+version = "1.0.0"
+
+[[handler]]
+route = "/"
+name = "site.wasm"
+files = ["static/*"]
+"#;
+
+    const BINDLE_ID: &str = "weather/1.0.0";
+
+    /// A build with four parcels, two of which share their content
+    fn weather_build() -> (tempfile::TempDir, Build) {
+        let (dir, facts) = source_dir(
+            FACTS,
+            &[
+                ("site.wasm", "site module"),
+                ("static/a.txt", "same"),
+                ("static/b.txt", "same"),
+                ("static/c.txt", "different"),
+            ],
+        );
+        let build = builder::build(&facts, dir.path(), &ExternalParcels::new()).unwrap();
+        (dir, build)
+    }
+
+    fn label<'a>(build: &'a Build, name: &str) -> &'a Label {
+        build
+            .invoice
+            .parcel
+            .iter()
+            .flatten()
+            .map(|p| &p.label)
+            .find(|l| l.name == name)
+            .unwrap()
+    }
+
+    /// The body Bindle sends to list missing parcels
+    fn missing_body(labels: &[&Label]) -> String {
+        #[derive(serde::Serialize)]
+        struct Missing<'a> {
+            missing: &'a [&'a Label],
+        }
+        toml::to_string(&Missing { missing: labels }).unwrap()
+    }
+
+    fn client(server: &Server) -> Client {
+        Client::new(ConnectionInfo {
+            url: server.url(),
+            danger_accept_invalid_certs: false,
+            api_key: None,
+        })
+    }
+
+    fn upload_path(label: &Label) -> String {
+        format!("/_i/{}@{}", BINDLE_ID, label.sha256)
+    }
+
+    #[tokio::test]
+    async fn uploads_each_missing_digest_once() {
+        let (_dir, build) = weather_build();
+        let site = label(&build, "site.wasm");
+        let a = label(&build, "static/a.txt");
+        let b = label(&build, "static/b.txt");
+
+        let mut server = Server::new_async().await;
+        let create = server
+            .mock("POST", "/_i")
+            .with_status(201)
+            .with_body(missing_body(&[site, a, b]))
+            .create_async()
+            .await;
+        let upload_site = server
+            .mock("POST", upload_path(site).as_str())
+            .match_body(Matcher::Exact("site module".to_owned()))
+            .expect(1)
+            .create_async()
+            .await;
+        // the server already has static/c.txt, so any other upload fails the push
+        let upload_same = server
+            .mock("POST", upload_path(a).as_str())
+            .match_body(Matcher::Exact("same".to_owned()))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let summary = client(&server).push(&build).await.unwrap();
+        create.assert_async().await;
+        upload_site.assert_async().await;
+        upload_same.assert_async().await;
+        assert_eq!(
+            (summary.uploaded, summary.uploaded_bytes),
+            (3, site.size + a.size + b.size)
+        );
+        assert_eq!((summary.skipped, summary.skipped_bytes), (1, 9));
+    }
+
+    #[tokio::test]
+    async fn a_parcel_the_server_already_has_is_not_an_error() {
+        let (_dir, build) = weather_build();
+        let site = label(&build, "site.wasm");
+
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/_i")
+            .with_status(201)
+            .with_body(missing_body(&[site]))
+            .create_async()
+            .await;
+        let upload = server
+            .mock("POST", upload_path(site).as_str())
+            .with_status(409)
+            .create_async()
+            .await;
+
+        let summary = client(&server).push(&build).await.unwrap();
+        upload.assert_async().await;
+        assert_eq!((summary.uploaded, summary.skipped), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn a_failed_upload_fails_the_push() {
+        let (_dir, build) = weather_build();
+        let site = label(&build, "site.wasm");
+
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/_i")
+            .with_status(201)
+            .with_body(missing_body(&[site]))
+            .create_async()
+            .await;
+        server
+            .mock("POST", upload_path(site).as_str())
+            .with_status(500)
+            .with_body("disk full")
+            .create_async()
+            .await;
+
+        let error = client(&server).push(&build).await.err().unwrap();
+        assert_eq!(
+            error.to_string(),
+            "Bindle server returned 500 Internal Server Error: disk full"
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_an_interrupted_push() {
+        let (_dir, build) = weather_build();
+        let site = label(&build, "site.wasm");
+
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/_i")
+            .with_status(409)
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("/_i/{}", BINDLE_ID).as_str())
+            .with_body(build.invoice.to_toml().unwrap())
+            .create_async()
+            .await;
+        let missing = server
+            .mock("GET", format!("/_r/missing/{}", BINDLE_ID).as_str())
+            .with_body(missing_body(&[site]))
+            .create_async()
+            .await;
+        let upload = server
+            .mock("POST", upload_path(site).as_str())
+            .expect(1)
+            .create_async()
+            .await;
+
+        let summary = client(&server).push(&build).await.unwrap();
+        missing.assert_async().await;
+        upload.assert_async().await;
+        assert_eq!((summary.uploaded, summary.skipped), (1, 3));
+    }
+
+    #[tokio::test]
+    async fn accepts_a_complete_existing_bindle() {
+        let (_dir, build) = weather_build();
+
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/_i")
+            .with_status(409)
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("/_i/{}", BINDLE_ID).as_str())
+            .with_body(build.invoice.to_toml().unwrap())
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("/_r/missing/{}", BINDLE_ID).as_str())
+            .with_body("missing = []\n")
+            .create_async()
+            .await;
+
+        let summary = client(&server).push(&build).await.unwrap();
+        assert_eq!((summary.uploaded, summary.skipped), (0, 4));
+    }
+
+    #[tokio::test]
+    async fn rejects_an_existing_bindle_with_a_different_invoice() {
+        let (_dir, build) = weather_build();
+        let mut existing = build.invoice.clone();
+        // the same parcels, but one file served with a different media type
+        existing.parcel.as_mut().unwrap()[1].label.media_type = "text/html".to_owned();
+
+        let mut server = Server::new_async().await;
+        server
+            .mock("POST", "/_i")
+            .with_status(409)
+            .create_async()
+            .await;
+        server
+            .mock("GET", format!("/_i/{}", BINDLE_ID).as_str())
+            .with_body(existing.to_toml().unwrap())
+            .create_async()
+            .await;
+
+        let error = client(&server).push(&build).await.err().unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("bindle weather/1.0.0 already exists with different contents"));
+    }
+}
@@ -0,0 +1,5 @@
+pub mod builder;
+pub mod client;
+pub mod invoice;
+
+pub use client::Client;
@@ -1,5 +1,7 @@
 use clap::Subcommand;
 
+use super::{PageArgs, SearchArgs};
+
 #[derive(Subcommand)]
 #[clap(alias = "a")]
 #[clap(alias = "apps")]
@@ -13,14 +15,36 @@ pub(crate) enum Commands {
         storage_id: String,
     },
 
+    /// Show the details of an application and its channels
+    #[clap(alias = "describe")]
+    Get {
+        /// The application name or ID
+        app: String,
+    },
+
     /// List all apps
-    List {},
+    List {
+        #[clap(flatten)]
+        paging: PageArgs,
+        #[clap(flatten)]
+        search: SearchArgs,
+    },
+
+    /// Rename an application
+    #[clap(alias = "edit")]
+    Update {
+        /// The application name or ID
+        app: String,
+        /// The new name of the application
+        #[clap(long)]
+        name: String,
+    },
 
     /// Remove an application
     #[clap(alias = "delete")]
     #[clap(alias = "rm")]
     Remove {
-        /// The application ID
-        id: String,
+        /// The application name or ID
+        app: String,
     },
 }
@@ -0,0 +1,20 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+#[clap(alias = "b")]
+pub(crate) enum Commands {
+    /// Build the bindle described by HIPPOFACTS and write it to a directory without uploading it
+    Prepare {
+        /// The directory containing the HIPPOFACTS file
+        #[clap(parse(from_os_str), default_value = ".")]
+        path: PathBuf,
+        /// The directory to write the invoice and parcels to
+        #[clap(short, long, parse(from_os_str))]
+        dir: PathBuf,
+        /// The URL of the Bindle server, needed only to resolve external handlers
+        #[clap(env = "BINDLE_URL", long)]
+        bindle_server: Option<String>,
+    },
+}
@@ -2,6 +2,8 @@ use std::path::PathBuf;
 
 use clap::Subcommand;
 
+use super::{parse_duration, PageArgs, SearchArgs};
+
 #[derive(Subcommand)]
 #[clap(alias = "cert")]
 #[clap(alias = "certs")]
@@ -20,14 +22,67 @@ pub(crate) enum Commands {
         private_key_path: PathBuf,
     },
 
+    /// Show the details of a TLS certificate and the channels using it
+    #[clap(alias = "describe")]
+    Get {
+        /// The certificate name or ID
+        certificate: String,
+    },
+
     // List all certificates
-    List {},
+    List {
+        #[clap(flatten)]
+        paging: PageArgs,
+        #[clap(flatten)]
+        search: SearchArgs,
+    },
+
+    /// List certificates with their expiry dates, failing if any expire soon
+    Expiring {
+        /// How soon a certificate must expire to fail the check
+        #[clap(long, default_value = "30d", value_parser = parse_duration)]
+        within: chrono::Duration,
+    },
+
+    /// Change a TLS certificate's name or keys in place
+    #[clap(alias = "edit")]
+    Update {
+        /// The certificate name or ID
+        certificate: String,
+        /// The new name of the certificate
+        #[clap(long)]
+        name: Option<String>,
+        /// The filepath to the new public key
+        #[clap(long, parse(from_os_str), requires = "private-key")]
+        public_key: Option<PathBuf>,
+        /// The filepath to the new private key
+        #[clap(long, parse(from_os_str), requires = "public-key")]
+        private_key: Option<PathBuf>,
+    },
+
+    /// Add a new TLS certificate and move every channel using an old one to it
+    Rotate {
+        /// The name or ID of the certificate to replace
+        certificate: String,
+        /// The filepath to the new public key
+        #[clap(parse(from_os_str), value_name = "PUBLIC_KEY")]
+        public_key_path: PathBuf,
+        /// The filepath to the new private key
+        #[clap(parse(from_os_str), value_name = "PRIVATE_KEY")]
+        private_key_path: PathBuf,
+        /// The name of the new certificate [default: the old name followed by today's date]
+        #[clap(long)]
+        name: Option<String>,
+        /// Remove the old certificate once no channels use it
+        #[clap(long)]
+        remove_old: bool,
+    },
 
     /// Remove a TLS certificate
     #[clap(alias = "delete")]
     #[clap(alias = "rm")]
     Remove {
-        /// The certificate ID
-        id: String,
+        /// The certificate name or ID
+        certificate: String,
     },
 }
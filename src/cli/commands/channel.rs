@@ -1,5 +1,7 @@
 use clap::Subcommand;
 
+use super::{parse_duration, PageArgs, SearchArgs};
+
 #[derive(Subcommand)]
 #[clap(alias = "c")]
 #[clap(alias = "channels")]
@@ -10,8 +12,8 @@ pub(crate) enum Commands {
         /// The name of the channel
         name: String,
 
-        /// The application ID this channel is bound to
-        app_id: String,
+        /// The name or ID of the application this channel is bound to
+        app: String,
 
         /// The domain name used to serve requests for this channel
         #[clap(short, long)]
@@ -21,29 +23,110 @@ pub(crate) enum Commands {
         #[clap(long)]
         range_rule: Option<String>,
 
-        /// if specified, informs hippo to deploy this revision and ONLY this revision
+        /// if specified, informs hippo to deploy this revision and ONLY this revision (a revision number or ID)
         #[clap(long)]
         revision_id: Option<String>,
 
-        /// the TLS certificate that should be bound to this channel
+        /// the TLS certificate that should be bound to this channel (a name or ID)
         #[clap(long)]
         certificate_id: Option<String>,
     },
 
+    /// Show the details of a channel, including its environment variables and status
+    #[clap(alias = "describe")]
+    Get {
+        /// The channel name (optionally qualified as app/channel) or ID
+        channel: String,
+    },
+
     // List all channels
-    List {},
+    List {
+        /// Only list the channels of this application, by name or ID
+        #[clap(long, conflicts_with = "page")]
+        app: Option<String>,
+        #[clap(flatten)]
+        paging: PageArgs,
+        #[clap(flatten)]
+        search: SearchArgs,
+    },
+
+    /// Update a channel's domain, revision selection or certificate
+    #[clap(alias = "edit")]
+    Update {
+        /// The channel name (optionally qualified as app/channel) or ID
+        channel: String,
+
+        /// The domain name used to serve requests for this channel
+        #[clap(short, long)]
+        domain: Option<String>,
+
+        /// if specified, informs hippo to deploy a revision that matches this rule
+        #[clap(long)]
+        range_rule: Option<String>,
+
+        /// if specified, informs hippo to deploy this revision and ONLY this revision (a revision number or ID)
+        #[clap(long)]
+        revision_id: Option<String>,
+
+        /// the TLS certificate that should be bound to this channel (a name or ID)
+        #[clap(long)]
+        certificate_id: Option<String>,
+
+        /// unbind the TLS certificate from this channel
+        #[clap(long, conflicts_with = "certificate-id")]
+        clear_certificate: bool,
+    },
+
+    /// Deploy the revision running in one channel to another channel of the same app
+    Promote {
+        /// The channel to take the revision from, such as helloworld/staging
+        from: String,
+
+        /// The channel to deploy the revision to, such as helloworld/production
+        to: String,
+
+        /// Set the destination's range rule to match exactly the revision, instead of pinning it
+        #[clap(long)]
+        range_rule: bool,
+
+        /// Promote without asking for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    /// Pin a channel to the revision before the one it is running
+    Rollback {
+        /// The channel name (optionally qualified as app/channel) or ID
+        channel: String,
+
+        /// The revision to roll back to (a revision number or ID), instead of the previous one
+        #[clap(long)]
+        to: Option<String>,
+    },
 
     /// Remove a channel
     #[clap(alias = "delete")]
     #[clap(alias = "rm")]
     Remove {
-        /// The channel ID
-        id: String,
+        /// The channel name (optionally qualified as app/channel) or ID
+        channel: String,
     },
 
     /// Fetch logs
     Logs {
-        /// The channel ID
-        id: String,
-    }
+        /// The channel name (optionally qualified as app/channel) or ID
+        channel: String,
+        /// Keep printing new lines as they are logged, until interrupted
+        #[clap(short, long)]
+        follow: bool,
+        /// Show only the last N lines to begin with
+        #[clap(long, value_name = "N")]
+        tail: Option<usize>,
+        /// Show only lines logged within this long, such as 10m or 2h, going by the timestamp at the start of each line
+        #[clap(long, value_parser = parse_duration)]
+        since: Option<chrono::Duration>,
+        /// Show only lines matching this regular expression
+        #[clap(long, value_name = "REGEX", value_parser = regex::Regex::new)]
+        grep: Option<regex::Regex>,
+    },
 }
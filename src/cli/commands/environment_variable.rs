@@ -1,4 +1,6 @@
-use clap::Subcommand;
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
 
 #[derive(Subcommand)]
 #[clap(alias = "e")]
@@ -7,30 +9,128 @@ use clap::Subcommand;
 #[clap(alias = "environmentvariable")]
 #[clap(alias = "environmentvariables")]
 pub(crate) enum Commands {
-    /// Add an environment variable
+    /// Add an environment variable that is not already set
     #[clap(alias = "new")]
+    #[clap(allow_missing_positional = true)]
+    #[clap(
+        override_usage = "hippo env add <KEY> <VALUE> <CHANNEL>\n    hippo env add <KEY> <CHANNEL> <--secret|--value-from-file <PATH>>"
+    )]
     Add {
         /// The environment variable key
         key: String,
-        /// The environment variable value
-        value: String,
-        /// The channel ID this environment variable will be bound to
-        channel_id: String,
+        /// The environment variable value; leave it out when using --secret or --value-from-file
+        #[clap(required_unless_present_any = &["secret", "value-from-file"])]
+        value: Option<String>,
+        /// The channel this environment variable will be bound to, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        #[clap(flatten)]
+        source: ValueSource,
+    },
+
+    /// Set an environment variable, replacing its value if it is already set
+    #[clap(allow_missing_positional = true)]
+    #[clap(
+        override_usage = "hippo env set <KEY> <VALUE> <CHANNEL>\n    hippo env set <KEY> <CHANNEL> <--secret|--value-from-file <PATH>>"
+    )]
+    Set {
+        /// The environment variable key
+        key: String,
+        /// The environment variable value; leave it out when using --secret or --value-from-file
+        #[clap(required_unless_present_any = &["secret", "value-from-file"])]
+        value: Option<String>,
+        /// The channel this environment variable will be bound to, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        #[clap(flatten)]
+        source: ValueSource,
+    },
+
+    /// Set a channel's environment variables from a dotenv file in a single update
+    Import {
+        /// The channel to set the variables on, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        /// The dotenv file to read
+        #[clap(long, parse(from_os_str), default_value = ".env")]
+        file: PathBuf,
+        /// Remove the channel's variables that are not in the file, instead of keeping them
+        #[clap(long)]
+        replace: bool,
+    },
+
+    /// Print a channel's environment variables in dotenv format
+    Export {
+        /// The channel to read the variables from, by name (optionally qualified as app/channel) or ID
+        channel: String,
+    },
+
+    /// Show how a channel's environment variables differ from a file
+    Diff {
+        /// The channel to compare, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        /// The variables the channel should have, as a TOML table of keys and values or a dotenv file
+        #[clap(long, parse(from_os_str), default_value = "env.toml")]
+        file: PathBuf,
+    },
+
+    /// Make a channel's environment variables match a file exactly, after showing what will change
+    Apply {
+        /// The channel to update, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        /// The variables the channel should have, as a TOML table of keys and values or a dotenv file
+        #[clap(long, parse(from_os_str), default_value = "env.toml")]
+        file: PathBuf,
+        /// Apply without asking for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+
+    /// Copy environment variables from one channel to another in a single update
+    Copy {
+        /// The channel to copy from, by name (optionally qualified as app/channel) or ID
+        from: String,
+        /// The channel to copy to, by name (optionally qualified as app/channel) or ID
+        to: String,
+        /// Copy only these keys, separated by commas
+        #[clap(long, use_value_delimiter = true)]
+        keys: Vec<String>,
+        /// Replace the values of keys the destination already has, instead of keeping them
+        #[clap(long)]
+        overwrite: bool,
     },
 
     // List all environment variables bound to a channel
     List {
-        /// The channel ID we want to lookup
-        channel_id: String,
+        /// The channel we want to lookup, by name (optionally qualified as app/channel) or ID
+        channel: String,
     },
 
     /// Remove an environment variable
+    #[clap(alias = "remove")]
     #[clap(alias = "delete")]
     #[clap(alias = "rm")]
-    Remove {
-        /// The channel ID we want to remove this environment variable from
-        channel_id: String,
-        /// The environment variable ID
-        id: String,
+    Unset {
+        /// The channel we want to remove this environment variable from, by name (optionally qualified as app/channel) or ID
+        channel: String,
+        /// The environment variable key
+        key: String,
+        /// Succeed even if the channel does not have the variable
+        #[clap(long)]
+        ignore_missing: bool,
     },
 }
+
+/// Ways of giving an environment variable's value without putting it on the
+/// command line, where it would be kept in shell history
+#[derive(Args)]
+pub(crate) struct ValueSource {
+    /// Prompt for the value without echoing it, or read it from stdin when not running in a terminal
+    #[clap(long, conflicts_with_all = &["value", "value-from-file"])]
+    pub secret: bool,
+    /// Read the value from a file, or from stdin if the path is -
+    #[clap(
+        long,
+        parse(from_os_str),
+        value_name = "PATH",
+        conflicts_with = "value"
+    )]
+    pub value_from_file: Option<PathBuf>,
+}
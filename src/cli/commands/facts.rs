@@ -0,0 +1,14 @@
+use std::path::PathBuf;
+
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+#[clap(alias = "hippofacts")]
+pub(crate) enum Commands {
+    /// Validate a HIPPOFACTS file without contacting the server
+    Check {
+        /// The HIPPOFACTS file, or the directory containing it
+        #[clap(parse(from_os_str), default_value = ".")]
+        path: PathBuf,
+    },
+}
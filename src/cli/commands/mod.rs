@@ -1,10 +1,18 @@
 pub(crate) mod app;
+pub(crate) mod bindle;
 pub(crate) mod certificate;
 pub(crate) mod channel;
 pub(crate) mod environment_variable;
+pub(crate) mod facts;
+pub(crate) mod profile;
 pub(crate) mod revision;
 
-use clap::Subcommand;
+use std::path::PathBuf;
+
+use clap::{Args, Subcommand};
+
+use crate::client::ListOptions;
+use crate::versioning::VersionStrategy;
 
 #[derive(Subcommand)]
 pub(crate) enum Commands {
@@ -12,6 +20,10 @@ pub(crate) enum Commands {
     #[clap(subcommand)]
     App(app::Commands),
 
+    /// Build bindles locally
+    #[clap(subcommand)]
+    Bindle(bindle::Commands),
+
     /// Add, update, and remove TLS Certificate
     #[clap(subcommand)]
     Certificate(certificate::Commands),
@@ -24,6 +36,10 @@ pub(crate) enum Commands {
     #[clap(subcommand)]
     Env(environment_variable::Commands),
 
+    /// Validate HIPPOFACTS files
+    #[clap(subcommand)]
+    Facts(facts::Commands),
+
     /// Log into Hippo
     Login {
         /// The URL to log into Hippo
@@ -38,11 +54,31 @@ pub(crate) enum Commands {
         /// Should invalid TLS certificates be accepted by the client?
         #[clap(env, short = 'k', long)]
         danger_accept_invalid_certs: bool,
+        /// A program to store the login token with, instead of the credentials file
+        #[clap(env = "HIPPO_CREDENTIAL_HELPER", long)]
+        credential_helper: Option<String>,
     },
 
     /// End the current Hippo login session
     Logout {},
 
+    /// Manage connection profiles for multiple Hippo servers
+    #[clap(subcommand)]
+    Profile(profile::Commands),
+
+    /// Build the application described by HIPPOFACTS, upload it to Bindle and register a new revision
+    Push {
+        /// The directory containing the HIPPOFACTS file
+        #[clap(parse(from_os_str), default_value = ".")]
+        path: PathBuf,
+        /// The URL of the Bindle server
+        #[clap(env = "BINDLE_URL", long)]
+        bindle_server: String,
+        /// How to derive the revision number from the HIPPOFACTS version
+        #[clap(long, value_enum, default_value = "exact")]
+        version_strategy: VersionStrategy,
+    },
+
     /// Create a new Hippo account
     Register {
         /// The Hippo URL
@@ -66,3 +102,151 @@ pub(crate) enum Commands {
     /// prints the logged in user
     Whoami {},
 }
+
+impl Commands {
+    /// Whether the command uses the selected profile's connection details
+    /// rather than creating or managing profiles itself
+    pub(crate) fn requires_profile(&self) -> bool {
+        !matches!(
+            self,
+            Commands::Login { .. } | Commands::Profile(_) | Commands::Register { .. }
+        )
+    }
+
+    /// Whether the command calls the Hippo API with the selected profile's token
+    pub(crate) fn requires_session(&self) -> bool {
+        matches!(
+            self,
+            Commands::App(_)
+                | Commands::Certificate(_)
+                | Commands::Channel(_)
+                | Commands::Env(_)
+                | Commands::Push { .. }
+                | Commands::Revision(_)
+        )
+    }
+
+    /// Whether the command only reads from Hippo, so it can safely be run
+    /// again from the start after logging in. `channel logs --follow` is
+    /// excluded, as it would print the lines it has already shown again.
+    pub(crate) fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Commands::App(app::Commands::Get { .. } | app::Commands::List { .. })
+                | Commands::Certificate(
+                    certificate::Commands::Get { .. }
+                        | certificate::Commands::List { .. }
+                        | certificate::Commands::Expiring { .. }
+                )
+                | Commands::Channel(
+                    channel::Commands::Get { .. }
+                        | channel::Commands::List { .. }
+                        | channel::Commands::Logs { follow: false, .. }
+                )
+                | Commands::Env(
+                    environment_variable::Commands::Export { .. }
+                        | environment_variable::Commands::Diff { .. }
+                        | environment_variable::Commands::List { .. }
+                )
+                | Commands::Revision(
+                    revision::Commands::Get { .. } | revision::Commands::List { .. }
+                )
+        )
+    }
+}
+
+/// Paging flags shared by the list commands
+#[derive(Args)]
+pub(crate) struct PageArgs {
+    /// The number of items to fetch per page
+    #[clap(long, value_parser = clap::value_parser!(i32).range(1..))]
+    pub page_size: Option<i32>,
+    /// The page to show, starting from 1
+    #[clap(long, conflicts_with = "all", value_parser = clap::value_parser!(i32).range(1..))]
+    pub page: Option<i32>,
+    /// Fetch and show every page
+    #[clap(long)]
+    pub all: bool,
+}
+
+impl PageArgs {
+    pub(crate) fn options(&self) -> ListOptions {
+        ListOptions {
+            page_index: self.page.map(|p| p - 1),
+            page_size: self.page_size,
+            ..Default::default()
+        }
+    }
+}
+
+/// Search and sort flags shared by the list commands whose API supports them
+#[derive(Args)]
+pub(crate) struct SearchArgs {
+    /// Only list items matching this text
+    #[clap(long)]
+    pub search: Option<String>,
+    /// The field to sort by, such as Name
+    #[clap(long)]
+    pub sort_by: Option<String>,
+    /// Sort in descending order
+    #[clap(long)]
+    pub desc: bool,
+}
+
+impl SearchArgs {
+    pub(crate) fn options(&self, paging: &PageArgs) -> ListOptions {
+        ListOptions {
+            search_text: self.search.clone(),
+            sort_by: self.sort_by.clone(),
+            sort_ascending: if self.desc || self.sort_by.is_some() {
+                Some(!self.desc)
+            } else {
+                None
+            },
+            ..paging.options()
+        }
+    }
+}
+
+/// Parses a span of time such as `30d`, `12h`, `90m` or `2w`
+pub(crate) fn parse_duration(text: &str) -> Result<chrono::Duration, String> {
+    let unit_at = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (amount, unit) = text.split_at(unit_at);
+    let amount: i64 = amount
+        .parse()
+        .map_err(|_| format!("'{}' does not start with a number", text))?;
+    match unit {
+        "s" => Ok(chrono::Duration::seconds(amount)),
+        "m" => Ok(chrono::Duration::minutes(amount)),
+        "h" => Ok(chrono::Duration::hours(amount)),
+        "d" => Ok(chrono::Duration::days(amount)),
+        "w" => Ok(chrono::Duration::weeks(amount)),
+        _ => Err(format!(
+            "'{}' needs a unit of s, m, h, d or w, such as 30d",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("45s"), Ok(chrono::Duration::seconds(45)));
+        assert_eq!(parse_duration("10m"), Ok(chrono::Duration::minutes(10)));
+        assert_eq!(parse_duration("2h"), Ok(chrono::Duration::hours(2)));
+        assert_eq!(parse_duration("30d"), Ok(chrono::Duration::days(30)));
+        assert_eq!(parse_duration("1w"), Ok(chrono::Duration::weeks(1)));
+    }
+
+    #[test]
+    fn rejects_durations_without_a_number_or_unit() {
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("10").is_err());
+        assert!(parse_duration("10 minutes").is_err());
+    }
+}
@@ -0,0 +1,29 @@
+use clap::Subcommand;
+
+#[derive(Subcommand)]
+#[clap(alias = "profiles")]
+pub(crate) enum Commands {
+    /// List all profiles
+    List {},
+
+    /// Make a profile the default for future commands
+    #[clap(alias = "switch")]
+    Use {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Remove a profile and its credentials
+    #[clap(alias = "delete")]
+    #[clap(alias = "rm")]
+    Remove {
+        /// The name of the profile
+        name: String,
+    },
+
+    /// Show the details of a profile
+    Show {
+        /// The name of the profile; defaults to the selected profile
+        name: Option<String>,
+    },
+}
@@ -1,5 +1,7 @@
 use clap::Subcommand;
 
+use super::PageArgs;
+
 #[derive(Subcommand)]
 #[clap(alias = "r")]
 #[clap(alias = "revisions")]
@@ -13,6 +15,16 @@ pub(crate) enum Commands {
         revision_number: String,
     },
 
+    /// Show the details of a revision and the channels running it
+    #[clap(alias = "describe")]
+    Get {
+        /// The revision ID, or the app name and revision number, such as helloworld/1.0.0
+        revision: String,
+    },
+
     // List all revisions
-    List {},
+    List {
+        #[clap(flatten)]
+        paging: PageArgs,
+    },
 }
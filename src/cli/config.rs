@@ -0,0 +1,176 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use hippo_openapi::models::TokenInfo;
+use serde::{Deserialize, Serialize};
+
+use super::credentials::{CredentialKey, CredentialStore};
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::BufReader,
+    path::Path,
+};
+
+pub(crate) const DEFAULT_PROFILE: &str = "default";
+
+#[derive(Serialize, Deserialize, Default)]
+pub(crate) struct HippoConfig {
+    pub current_profile: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+/// The connection details for one Hippo server. The login token itself is
+/// kept in a credential store, not in the config file.
+#[derive(Serialize, Deserialize, Clone)]
+pub(crate) struct Profile {
+    pub danger_accept_invalid_certs: bool,
+    /// Filled in from the credential store when the profile is used. Config
+    /// files written by earlier versions store the token here in plaintext.
+    #[serde(default, skip_serializing)]
+    pub token_info: Option<TokenInfo>,
+    pub username: String,
+    pub url: String,
+    /// The ID of this profile's token in the credential store, if logged in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential: Option<String>,
+    /// A credential helper program to store the token with, instead of the credentials file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credential_helper: Option<String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            danger_accept_invalid_certs: false,
+            token_info: None,
+            username: "".to_owned(),
+            url: "http://localhost:5309".to_owned(),
+            credential: None,
+            credential_helper: None,
+        }
+    }
+}
+
+impl Profile {
+    pub fn credential_store(&self, config_path: &Path) -> CredentialStore {
+        CredentialStore::new(self.credential_helper.as_deref(), config_path)
+    }
+
+    pub fn credential_key<'a>(&'a self, id: &'a str) -> CredentialKey<'a> {
+        CredentialKey {
+            id,
+            url: &self.url,
+            username: &self.username,
+        }
+    }
+
+    /// Saves the token in the credential store and records where it is
+    pub fn store_token(
+        &mut self,
+        profile_name: &str,
+        config_path: &Path,
+        token_info: TokenInfo,
+    ) -> anyhow::Result<()> {
+        self.credential_store(config_path)
+            .store(&self.credential_key(profile_name), &token_info)?;
+        self.credential = Some(profile_name.to_owned());
+        self.token_info = Some(token_info);
+        Ok(())
+    }
+
+    /// Loads the token from the credential store, if the profile is logged in
+    pub fn load_token(&mut self, config_path: &Path) -> anyhow::Result<()> {
+        if self.token_info.is_some() {
+            return Ok(());
+        }
+        if let Some(id) = &self.credential {
+            self.token_info = self
+                .credential_store(config_path)
+                .get(&self.credential_key(id))?;
+        }
+        Ok(())
+    }
+
+    /// Removes the token from the credential store
+    pub fn erase_token(&mut self, config_path: &Path) -> anyhow::Result<()> {
+        if let Some(id) = &self.credential {
+            self.credential_store(config_path)
+                .erase(&self.credential_key(id))?;
+        }
+        self.credential = None;
+        self.token_info = None;
+        Ok(())
+    }
+
+    /// When the stored token expires, if the server said so in a format we understand
+    pub fn token_expiry(&self) -> Option<DateTime<Utc>> {
+        let expiration = self.token_info.as_ref()?.expiration.as_ref()?;
+        match DateTime::parse_from_rfc3339(expiration) {
+            Ok(dt) => Some(dt.with_timezone(&Utc)),
+            // Hippo may omit the offset, in which case the time is UTC
+            Err(_) => NaiveDateTime::parse_from_str(expiration, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| DateTime::from_naive_utc_and_offset(dt, Utc)),
+        }
+    }
+}
+
+impl HippoConfig {
+    /// Loads the config file, if it exists. Config files from earlier
+    /// versions are upgraded and rewritten: one written before profiles
+    /// existed holds a single profile's fields at the top level, which become
+    /// the default profile, and plaintext tokens are moved to the credential
+    /// store.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+
+        // TODO: switch from std::fs to tokio::fs once serde_json implements tokio support
+        // https://github.com/serde-rs/json/issues/316
+        let file = File::open(path)?;
+        let value: serde_json::Value = serde_json::from_reader(BufReader::new(file))?;
+        let (mut config, mut upgraded) =
+            if value.get("profiles").is_some() || value.get("url").is_none() {
+                (serde_json::from_value(value)?, false)
+            } else {
+                let legacy: Profile = serde_json::from_value(value)?;
+                let mut config = Self {
+                    current_profile: Some(DEFAULT_PROFILE.to_owned()),
+                    profiles: BTreeMap::new(),
+                };
+                config.profiles.insert(DEFAULT_PROFILE.to_owned(), legacy);
+                (config, true)
+            };
+
+        for (name, profile) in config.profiles.iter_mut() {
+            if profile.credential.is_none() {
+                if let Some(token_info) = profile.token_info.take() {
+                    profile.store_token(name, path, token_info)?;
+                    upgraded = true;
+                }
+            }
+        }
+
+        if upgraded {
+            config.save(path)?;
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if !path.exists() && path.ancestors().count() != 0 {
+            fs::create_dir_all(path.parent().unwrap())?;
+        }
+        serde_json::to_writer(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// The profile named on the command line, otherwise the current profile
+    pub fn selected_profile_name(&self, requested: Option<&str>) -> String {
+        requested
+            .or(self.current_profile.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_owned()
+    }
+}
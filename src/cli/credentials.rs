@@ -0,0 +1,175 @@
+use hippo_openapi::models::TokenInfo;
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+pub(crate) const CREDENTIALS_FILE_NAME: &str = "credentials.json";
+
+/// Identifies a stored token. The ID is what the config file records; the
+/// URL and username are passed to credential helpers for their own bookkeeping.
+pub(crate) struct CredentialKey<'a> {
+    pub id: &'a str,
+    pub url: &'a str,
+    pub username: &'a str,
+}
+
+/// Where login tokens are kept, so they need not be stored in the config file
+pub(crate) enum CredentialStore {
+    /// A JSON file readable only by the current user
+    File(PathBuf),
+    /// An external program speaking the credential helper protocol: it is run
+    /// with `get`, `store` or `erase` as its last argument, and reads and
+    /// writes `key=value` lines on stdin and stdout, like git credential helpers
+    Helper(String),
+}
+
+impl CredentialStore {
+    pub fn new(helper: Option<&str>, config_path: &Path) -> Self {
+        match helper {
+            Some(h) => Self::Helper(h.to_owned()),
+            None => Self::File(config_path.with_file_name(CREDENTIALS_FILE_NAME)),
+        }
+    }
+
+    pub fn get(&self, key: &CredentialKey) -> anyhow::Result<Option<TokenInfo>> {
+        match self {
+            Self::File(path) => Ok(read_credentials_file(path)?.remove(key.id)),
+            Self::Helper(helper) => {
+                let output = run_helper(helper, "get", &key_fields(key))?;
+                let mut fields: BTreeMap<String, String> = output
+                    .lines()
+                    .filter_map(|l| l.split_once('='))
+                    .map(|(k, v)| (k.to_owned(), v.to_owned()))
+                    .collect();
+                Ok(fields.remove("token").map(|token| TokenInfo {
+                    token: Some(token),
+                    expiration: fields.remove("expiration"),
+                }))
+            }
+        }
+    }
+
+    pub fn store(&self, key: &CredentialKey, token_info: &TokenInfo) -> anyhow::Result<()> {
+        match self {
+            Self::File(path) => {
+                let mut credentials = read_credentials_file(path)?;
+                credentials.insert(key.id.to_owned(), token_info.clone());
+                write_credentials_file(path, &credentials)
+            }
+            Self::Helper(helper) => {
+                let mut fields = key_fields(key);
+                if let Some(token) = &token_info.token {
+                    fields.push(("token", token));
+                }
+                if let Some(expiration) = &token_info.expiration {
+                    fields.push(("expiration", expiration));
+                }
+                run_helper(helper, "store", &fields).map(|_| ())
+            }
+        }
+    }
+
+    pub fn erase(&self, key: &CredentialKey) -> anyhow::Result<()> {
+        match self {
+            Self::File(path) => {
+                let mut credentials = read_credentials_file(path)?;
+                if credentials.remove(key.id).is_some() {
+                    write_credentials_file(path, &credentials)?;
+                }
+                Ok(())
+            }
+            Self::Helper(helper) => run_helper(helper, "erase", &key_fields(key)).map(|_| ()),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Self::File(path) => format!("file {}", path.display()),
+            Self::Helper(helper) => format!("helper '{}'", helper),
+        }
+    }
+}
+
+fn key_fields<'a>(key: &'a CredentialKey) -> Vec<(&'static str, &'a str)> {
+    vec![("id", key.id), ("url", key.url), ("username", key.username)]
+}
+
+/// Runs the helper through the shell, as git does, so that it can be given
+/// arguments and a path containing spaces can be quoted
+fn shell_command(helper: &str, action: &str) -> Command {
+    if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C").arg(format!("{} {}", helper, action));
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg(format!("{} \"$@\"", helper))
+            .arg(helper)
+            .arg(action);
+        command
+    }
+}
+
+fn run_helper(helper: &str, action: &str, fields: &[(&str, &str)]) -> anyhow::Result<String> {
+    if helper.trim().is_empty() {
+        anyhow::bail!("the credential helper command is empty");
+    }
+    let mut child = shell_command(helper, action)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("cannot run credential helper '{}': {}", helper, e))?;
+
+    let mut input = String::new();
+    for (k, v) in fields {
+        input.push_str(&format!("{}={}\n", k, v));
+    }
+    input.push('\n');
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "credential helper '{}' failed to {} the token ({})",
+            helper,
+            action,
+            output.status
+        );
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+fn read_credentials_file(path: &Path) -> anyhow::Result<BTreeMap<String, TokenInfo>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let file = File::open(path)?;
+    serde_json::from_reader(BufReader::new(file))
+        .map_err(|e| anyhow::anyhow!("invalid credentials file {}: {}", path.display(), e))
+}
+
+fn write_credentials_file(
+    path: &Path,
+    credentials: &BTreeMap<String, TokenInfo>,
+) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // the mode only applies to new files, so tighten up existing ones too
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    serde_json::to_writer(options.open(path)?, credentials)?;
+    Ok(())
+}
@@ -0,0 +1,289 @@
+use hippo_openapi::models::{
+    AppItem, CertificateItem, ChannelItem, ChannelJobStatusItem, ChannelRevisionSelectionStrategy,
+    RevisionItem,
+};
+
+use super::output::{or_none, print_fields, print_table};
+
+pub(crate) fn app(app: &AppItem, channels: &[ChannelItem]) {
+    print_fields(&[
+        ("Name", app.name.clone()),
+        ("ID", app.id.clone()),
+        ("Storage ID", app.storage_id.clone()),
+        ("Description", or_none(app.description.as_deref())),
+    ]);
+    println!("Channels:");
+    if channels.is_empty() {
+        println!("  <none>");
+        return;
+    }
+    let rows: Vec<_> = channels
+        .iter()
+        .map(|c| {
+            vec![
+                c.name.clone(),
+                c.id.clone(),
+                or_none(Some(&c.domain)),
+                active_revision(c),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "ID", "DOMAIN", "ACTIVE REVISION"], &rows, 2);
+}
+
+pub(crate) fn channel(channel: &ChannelItem, status: Option<&ChannelJobStatusItem>) {
+    let app = match &channel.app_summary {
+        Some(a) => format!("{} ({})", a.name, a.id),
+        None => channel.app_id.clone(),
+    };
+    let certificate = channel
+        .certificate
+        .as_ref()
+        .map(|c| format!("{} ({})", c.name, c.id));
+    print_fields(&[
+        ("Name", channel.name.clone()),
+        ("ID", channel.id.clone()),
+        ("App", app),
+        ("Domain", or_none(Some(&channel.domain))),
+        (
+            "Status",
+            or_none(status.map(|s| s.status.to_string()).as_deref()),
+        ),
+        ("Revision selection", revision_selection(channel)),
+        ("Active revision", active_revision(channel)),
+        ("Certificate", or_none(certificate.as_deref())),
+        (
+            "Last published",
+            or_none(channel.last_publish_at.as_deref()),
+        ),
+    ]);
+    println!("Environment variables:");
+    if channel.environment_variables.is_empty() {
+        println!("  <none>");
+        return;
+    }
+    let rows: Vec<_> = channel
+        .environment_variables
+        .iter()
+        .map(|e| vec![e.key.clone(), e.value.clone()])
+        .collect();
+    print_table(&["KEY", "VALUE"], &rows, 2);
+}
+
+pub(crate) fn certificate(certificate: &CertificateItem) {
+    print_fields(&[
+        ("Name", certificate.name.clone()),
+        ("ID", certificate.id.clone()),
+    ]);
+    println!("Channels:");
+    if certificate.channels.is_empty() {
+        println!("  <none>");
+        return;
+    }
+    let rows: Vec<_> = certificate
+        .channels
+        .iter()
+        .map(|c| vec![c.name.clone(), c.id.clone(), or_none(Some(&c.domain))])
+        .collect();
+    print_table(&["NAME", "ID", "DOMAIN"], &rows, 2);
+}
+
+pub(crate) fn revision(revision: &RevisionItem, app: Option<&AppItem>, channels: &[ChannelItem]) {
+    let app = match app {
+        Some(a) => format!("{} ({})", a.name, a.id),
+        None => revision.app_id.clone(),
+    };
+    let channels = channels
+        .iter()
+        .map(|c| c.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    print_fields(&[
+        ("Revision", revision.revision_number.clone()),
+        ("ID", revision.id.clone()),
+        ("App", app),
+        ("Type", or_none(revision._type.as_deref())),
+        ("Active in", or_none(Some(&channels))),
+    ]);
+    println!("Components:");
+    if revision.components.is_empty() {
+        println!("  <none>");
+        return;
+    }
+    let rows: Vec<_> = revision
+        .components
+        .iter()
+        .map(|c| {
+            vec![
+                c.name.clone(),
+                or_none(c.route.as_deref()),
+                c.source.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "ROUTE", "SOURCE"], &rows, 2);
+}
+
+/// The channel's name qualified by its app's name, such as `helloworld/latest`
+pub(crate) fn qualified_name(channel: &ChannelItem) -> String {
+    match &channel.app_summary {
+        Some(a) => format!("{}/{}", a.name, channel.name),
+        None => channel.name.clone(),
+    }
+}
+
+/// The revision settings that differ between two versions of a channel, as
+/// `(label, before, after)`
+pub(crate) fn channel_changes(
+    before: &ChannelItem,
+    after: &ChannelItem,
+) -> Vec<(&'static str, String, String)> {
+    vec![
+        (
+            "Revision selection",
+            revision_selection(before),
+            revision_selection(after),
+        ),
+        (
+            "Active revision",
+            active_revision(before),
+            active_revision(after),
+        ),
+    ]
+    .into_iter()
+    .filter(|(_, before, after)| before != after)
+    .collect()
+}
+
+/// How a channel's environment variables would change, as `(key, before, after)`
+/// with `None` for a variable that is added or removed. A key set more than
+/// once before and once after counts as changed even if its value is the same,
+/// since the update removes its duplicates.
+pub(crate) fn variable_changes(
+    before: &[(String, String)],
+    after: &[(String, String)],
+) -> Vec<(String, Option<String>, Option<String>)> {
+    let value_in = |variables: &[(String, String)], key: &str| {
+        variables
+            .iter()
+            .rev()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.clone())
+    };
+    let count_in = |variables: &[(String, String)], key: &str| {
+        variables.iter().filter(|(k, _)| k == key).count()
+    };
+    let mut changes: Vec<(String, Option<String>, Option<String>)> = vec![];
+    for (key, value) in after {
+        if changes.iter().any(|(k, _, _)| k == key) {
+            continue;
+        }
+        let previous = value_in(before, key);
+        if previous.as_ref() != Some(value) || count_in(before, key) != count_in(after, key) {
+            changes.push((key.clone(), previous, value_in(after, key)));
+        }
+    }
+    for (key, value) in before {
+        if value_in(after, key).is_none() && !changes.iter().any(|(k, _, _)| k == key) {
+            changes.push((key.clone(), Some(value.clone()), None));
+        }
+    }
+    changes
+}
+
+fn revision_selection(channel: &ChannelItem) -> String {
+    match channel.revision_selection_strategy {
+        ChannelRevisionSelectionStrategy::UseRangeRule => {
+            format!(
+                "range rule '{}'",
+                channel.range_rule.as_deref().unwrap_or("")
+            )
+        }
+        ChannelRevisionSelectionStrategy::UseSpecifiedRevision => "pinned revision".to_owned(),
+    }
+}
+
+fn active_revision(channel: &ChannelItem) -> String {
+    match &channel.active_revision {
+        Some(r) => format!("{} ({})", r.revision_number, r.id),
+        None => "<none>".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn change(
+        key: &str,
+        before: Option<&str>,
+        after: Option<&str>,
+    ) -> (String, Option<String>, Option<String>) {
+        (
+            key.to_owned(),
+            before.map(str::to_owned),
+            after.map(str::to_owned),
+        )
+    }
+
+    #[test]
+    fn lists_changed_added_and_removed_variables() {
+        let before = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+            ("C".to_string(), "3".to_string()),
+        ];
+        let after = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "20".to_string()),
+            ("D".to_string(), "4".to_string()),
+        ];
+        assert_eq!(
+            variable_changes(&before, &after),
+            vec![
+                change("B", Some("2"), Some("20")),
+                change("D", None, Some("4")),
+                change("C", Some("3"), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn collapsing_duplicates_is_a_change() {
+        let before = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ];
+        let after = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+        ];
+        assert_eq!(
+            variable_changes(&before, &after),
+            vec![change("A", Some("1"), Some("1"))]
+        );
+    }
+
+    #[test]
+    fn compares_against_the_last_duplicate() {
+        let before = vec![
+            ("A".to_string(), "1".to_string()),
+            ("A".to_string(), "2".to_string()),
+        ];
+        let after = vec![("A".to_string(), "3".to_string())];
+        assert_eq!(
+            variable_changes(&before, &after),
+            vec![change("A", Some("2"), Some("3"))]
+        );
+    }
+
+    #[test]
+    fn unchanged_duplicates_are_not_a_change() {
+        let variables = vec![
+            ("A".to_string(), "1".to_string()),
+            ("A".to_string(), "1".to_string()),
+        ];
+        assert!(variable_changes(&variables, &variables).is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+
+/// Selects the log lines that `channel logs --since` and `--grep` ask for
+pub(crate) struct LogFilter {
+    since: Option<DateTime<Utc>>,
+    grep: Option<Regex>,
+    /// Whether the last timestamped line was recent enough; lines without a
+    /// timestamp, such as the rest of a stack trace, go with the line before them
+    recent: bool,
+}
+
+impl LogFilter {
+    pub(crate) fn new(since: Option<DateTime<Utc>>, grep: Option<Regex>) -> Self {
+        LogFilter {
+            since,
+            grep,
+            recent: true,
+        }
+    }
+
+    pub(crate) fn select<'a>(&mut self, lines: &'a [String]) -> Vec<&'a String> {
+        lines.iter().filter(|line| self.matches(line)).collect()
+    }
+
+    fn matches(&mut self, line: &str) -> bool {
+        if let Some(since) = self.since {
+            if let Some(timestamp) = timestamp(line) {
+                self.recent = timestamp >= since;
+            }
+            if !self.recent {
+                return false;
+            }
+        }
+        match &self.grep {
+            Some(grep) => grep.is_match(line),
+            None => true,
+        }
+    }
+}
+
+/// The lines of `current` that come after those already seen in `previous`.
+///
+/// The server returns the whole recent log each time, so this looks for the
+/// longest run of lines that ends `previous` and begins `current`. If there is
+/// none, as when the channel has restarted, every line is new.
+pub(crate) fn new_lines<'a>(previous: &[String], current: &'a [String]) -> &'a [String] {
+    let longest = previous.len().min(current.len());
+    let overlap = (1..=longest)
+        .rev()
+        .find(|&n| previous[previous.len() - n..] == current[..n])
+        .unwrap_or(0);
+    &current[overlap..]
+}
+
+/// The time at the start of a log line, written as RFC 3339 or as
+/// `YYYY-MM-DD HH:MM:SS` in UTC, optionally in square brackets
+fn timestamp(line: &str) -> Option<DateTime<Utc>> {
+    let line = line.trim_start().trim_start_matches('[');
+    let mut words = line.split_whitespace();
+    let first = words.next()?.trim_end_matches(']');
+    if let Ok(t) = DateTime::parse_from_rfc3339(first) {
+        return Some(t.with_timezone(&Utc));
+    }
+    let second = words.next()?.trim_end_matches(']');
+    NaiveDateTime::parse_from_str(&format!("{} {}", first, second), "%Y-%m-%d %H:%M:%S%.f")
+        .ok()
+        .map(|t| DateTime::from_naive_utc_and_offset(t, Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(ls: &[&str]) -> Vec<String> {
+        ls.iter().map(|l| l.to_string()).collect()
+    }
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn new_lines_follow_the_overlap() {
+        let previous = lines(&["a", "b", "c"]);
+        let current = lines(&["b", "c", "d", "e"]);
+        assert_eq!(new_lines(&previous, &current), &current[2..]);
+    }
+
+    #[test]
+    fn new_lines_uses_the_longest_overlap() {
+        // "x" alone also ends previous and begins current, but the whole log repeats
+        let previous = lines(&["x", "y", "x"]);
+        let current = lines(&["x", "y", "x", "z"]);
+        assert_eq!(new_lines(&previous, &current), &current[3..]);
+    }
+
+    #[test]
+    fn new_lines_is_empty_when_nothing_changed() {
+        let previous = lines(&["a", "b"]);
+        assert!(new_lines(&previous, &previous).is_empty());
+    }
+
+    #[test]
+    fn every_line_is_new_without_an_overlap() {
+        let previous = lines(&["a", "b"]);
+        let current = lines(&["c", "d"]);
+        assert_eq!(new_lines(&previous, &current), &current[..]);
+        assert_eq!(new_lines(&[], &current), &current[..]);
+    }
+
+    #[test]
+    fn reads_timestamps_at_the_start_of_lines() {
+        assert_eq!(
+            timestamp("2022-07-21T09:05:03+01:00 started"),
+            Some(at("2022-07-21T08:05:03Z"))
+        );
+        assert_eq!(
+            timestamp("[2022-07-21 09:05:03.250] started"),
+            Some(at("2022-07-21T09:05:03.25Z"))
+        );
+        assert_eq!(
+            timestamp("  2022-07-21 09:05:03 started"),
+            Some(at("2022-07-21T09:05:03Z"))
+        );
+        assert_eq!(timestamp("started at 2022-07-21 09:05:03"), None);
+        assert_eq!(timestamp(""), None);
+    }
+
+    #[test]
+    fn since_keeps_untimestamped_lines_with_the_line_before() {
+        let log = lines(&[
+            "2022-07-21 09:00:00 old",
+            "  old detail",
+            "2022-07-21 10:00:00 new",
+            "  new detail",
+        ]);
+        let mut filter = LogFilter::new(Some(at("2022-07-21T09:30:00Z")), None);
+        assert_eq!(filter.select(&log), vec![&log[2], &log[3]]);
+    }
+
+    #[test]
+    fn grep_applies_after_since() {
+        let log = lines(&[
+            "2022-07-21 09:00:00 error: old",
+            "2022-07-21 10:00:00 error: new",
+            "2022-07-21 10:00:01 ok",
+        ]);
+        let mut filter = LogFilter::new(
+            Some(at("2022-07-21T09:30:00Z")),
+            Some(Regex::new("error").unwrap()),
+        );
+        assert_eq!(filter.select(&log), vec![&log[1]]);
+    }
+}
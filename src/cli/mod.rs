@@ -1,25 +1,50 @@
 mod commands;
+mod config;
+mod credentials;
+mod describe;
+mod logs;
+mod output;
+mod session;
 
 use commands::{
-    app::Commands as AppCommands, certificate::Commands as CertificateCommands,
-    channel::Commands as ChannelCommands, environment_variable::Commands as EnvCommands,
-    revision::Commands as RevisionCommands, Commands,
+    app::Commands as AppCommands,
+    bindle::Commands as BindleCommands,
+    certificate::Commands as CertificateCommands,
+    channel::Commands as ChannelCommands,
+    environment_variable::{Commands as EnvCommands, ValueSource},
+    facts::Commands as FactsCommands,
+    profile::Commands as ProfileCommands,
+    revision::Commands as RevisionCommands,
+    Commands,
 };
+use config::{HippoConfig, Profile};
+use output::OutputFormat;
 
-use crate::client::{Client, ConnectionInfo};
+use crate::bindle::{self, builder};
+use crate::client::{self, Client, ConnectionInfo, NotFound, Unauthorized, VariableNotSet};
+use crate::dotenv;
+use crate::hippofacts::{HippoFacts, HIPPOFACTS_FILE_NAME};
+use crate::tls;
+use crate::versioning::{self, VersionStrategy};
 
 use clap::Parser;
-use dialoguer::{Input, Password};
+use dialoguer::{Confirm, Input, Password};
 use dirs::config_dir;
-use hippo_openapi::models::{ChannelRevisionSelectionStrategy, TokenInfo};
+use futures::{future, TryStreamExt};
+use hippo_openapi::models::{
+    ChannelRevisionSelectionStrategy, ChannelRevisionSelectionStrategyField,
+    EnvironmentVariableItem, GuidNullableField, PatchChannelCommand, StringField,
+};
 use log::LevelFilter;
-use serde::{Deserialize, Serialize};
 use std::{
-    fs::{self, File},
-    io::BufReader,
-    path::PathBuf,
+    fs,
+    io::Read,
+    path::{Path, PathBuf},
 };
 
+/// How often `channel logs --follow` checks for new lines
+const LOG_POLL_SECONDS: u64 = 2;
+
 const ABOUT_HIPPO: &str = r#"Create and manage Hippo applications.
 
 The hippo commandline utility provides many tools for managing Hippo applications,
@@ -30,25 +55,6 @@ Many 'hippo' commands operate on a 'HIPPOFACTS' TOML file located in the same di
 in which you are running the 'hippo' command.
 "#;
 
-#[derive(Serialize, Deserialize)]
-struct HippoConfig {
-    danger_accept_invalid_certs: bool,
-    token_info: Option<TokenInfo>,
-    username: String,
-    url: String,
-}
-
-impl Default for HippoConfig {
-    fn default() -> Self {
-        Self {
-            danger_accept_invalid_certs: false,
-            token_info: None,
-            username: "".to_owned(),
-            url: "http://localhost:5309".to_owned(),
-        }
-    }
-}
-
 /// The Hippo commandline client
 #[derive(Parser)]
 #[clap(name = "hippo")]
@@ -58,6 +64,18 @@ pub struct Cli {
     #[clap(short, long, parse(from_os_str), value_name = "FILE")]
     config: Option<PathBuf>,
 
+    /// The connection profile to use instead of the current one
+    #[clap(long, global = true, env = "HIPPO_PROFILE")]
+    profile: Option<String>,
+
+    /// The format for listing resources
+    #[clap(short, long, global = true, value_enum, default_value = "table")]
+    output: OutputFormat,
+
+    /// Show environment variable values and certificate private keys instead of masking them
+    #[clap(long, global = true)]
+    reveal: bool,
+
     /// Turn debugging information on
     #[clap(short, long, parse(from_occurrences))]
     verbose: usize,
@@ -70,21 +88,24 @@ impl Cli {
     pub async fn execute(&self) -> anyhow::Result<()> {
         let hippo_config_path = match &self.config {
             Some(p) => p.clone(),
-            None => PathBuf::from(
-                config_dir()
-                    .map(|h| h.join("hippo").join("hippo.json"))
-                    .unwrap(),
-            ),
+            None => config_dir()
+                .map(|h| h.join("hippo").join("hippo.json"))
+                .unwrap(),
         };
 
-        // TODO: switch from std::fs to tokio::fs once serde_json implements tokio support
-        // https://github.com/serde-rs/json/issues/316
-        let mut hippo_conf: HippoConfig = Default::default();
-        if hippo_config_path.exists() {
-            let file = File::open(hippo_config_path.clone())?;
-            let reader = BufReader::new(file);
-            hippo_conf = serde_json::from_reader(reader)?;
-        }
+        let mut hippo_conf = HippoConfig::load(&hippo_config_path)?;
+        let profile_name = hippo_conf.selected_profile_name(self.profile.as_deref());
+        let mut profile = match hippo_conf.profiles.get(&profile_name) {
+            Some(p) => p.clone(),
+            None if self.profile.is_some() && self.command.requires_profile() => {
+                anyhow::bail!(
+                    "profile '{}' does not exist; create it with 'hippo login --profile {}'",
+                    profile_name,
+                    profile_name
+                )
+            }
+            None => Profile::default(),
+        };
 
         let mut builder = env_logger::builder();
         builder.parse_default_env();
@@ -99,29 +120,140 @@ impl Cli {
 
         builder.init();
 
-        let hippo_client = Client::new(ConnectionInfo {
-            url: hippo_conf.url,
-            danger_accept_invalid_certs: hippo_conf.danger_accept_invalid_certs,
-            api_key: hippo_conf.token_info.map_or(None, |t| t.token),
-        });
+        if self.command.requires_session() || matches!(self.command, Commands::Whoami {}) {
+            profile.load_token(&hippo_config_path)?;
+        }
+        let profile = if self.command.requires_session() {
+            session::ensure_session(&mut hippo_conf, &hippo_config_path, &profile_name, profile)
+                .await?
+        } else {
+            profile
+        };
 
+        let result = self
+            .run(
+                &profile_client(&profile),
+                &mut hippo_conf,
+                &hippo_config_path,
+                &profile_name,
+                &profile,
+            )
+            .await;
+        match result {
+            Err(e) if e.is::<Unauthorized>() && self.command.requires_session() => {
+                // a command that changes things may have done part of its work,
+                // which running it again from the start would repeat
+                if !self.command.is_read_only() || !session::is_interactive() {
+                    return Err(session::session_expired(&profile_name));
+                }
+                eprintln!(
+                    "Your session for profile '{}' is no longer valid.",
+                    profile_name
+                );
+                if !Confirm::new()
+                    .with_prompt("Log in again and retry the command?")
+                    .default(true)
+                    .interact()?
+                {
+                    return Err(session::session_expired(&profile_name));
+                }
+                let profile =
+                    session::renew(&mut hippo_conf, &hippo_config_path, &profile_name, profile)
+                        .await?;
+                self.run(
+                    &profile_client(&profile),
+                    &mut hippo_conf,
+                    &hippo_config_path,
+                    &profile_name,
+                    &profile,
+                )
+                .await
+            }
+            result => result,
+        }
+    }
+
+    async fn run(
+        &self,
+        hippo_client: &Client,
+        hippo_conf: &mut HippoConfig,
+        hippo_config_path: &Path,
+        profile_name: &str,
+        profile: &Profile,
+    ) -> anyhow::Result<()> {
         match &self.command {
             Commands::App(AppCommands::Add { name, storage_id }) => {
                 let id = hippo_client
                     .add_app(name.to_owned(), storage_id.to_owned())
                     .await?;
                 println!("Added {} (ID = '{}')", name, id);
-                println!("IMPORTANT: save this App ID for later - you will need it to update and/or delete the App");
             }
 
-            Commands::App(AppCommands::List {}) => {
-                let apps = hippo_client.list_apps().await?;
-                println!("{}", serde_json::to_string_pretty(&apps.items)?);
+            Commands::App(AppCommands::Get { app }) => {
+                let id = hippo_client.resolve_app(app).await?;
+                let app = hippo_client.get_app(&id).await?;
+                let channels: Vec<_> = hippo_client
+                    .list_all_channels()
+                    .await?
+                    .into_iter()
+                    .filter(|c| c.app_id == app.id)
+                    .collect();
+                output::print_item(&app, &app.id, self.output, || {
+                    describe::app(&app, &channels)
+                })?;
+            }
+
+            Commands::App(AppCommands::List { paging, search }) => {
+                let options = search.options(paging);
+                let apps = if paging.all {
+                    hippo_client.apps(options).try_collect().await?
+                } else {
+                    output::page_items(hippo_client.list_apps_page(&options).await?)
+                };
+                output::print_list(&apps, self.output)?;
+            }
+
+            Commands::App(AppCommands::Update { app, name }) => {
+                let id = hippo_client.resolve_app(app).await?;
+                hippo_client.update_app(id, name.to_owned()).await?;
+                println!("Renamed {} to {}", app, name);
             }
 
-            Commands::App(AppCommands::Remove { id }) => {
-                hippo_client.remove_app(id.to_owned()).await?;
-                println!("Removed {}", id);
+            Commands::App(AppCommands::Remove { app }) => {
+                let id = hippo_client.resolve_app(app).await?;
+                hippo_client.remove_app(id).await?;
+                println!("Removed {}", app);
+            }
+
+            Commands::Bindle(BindleCommands::Prepare {
+                path,
+                dir,
+                bindle_server,
+            }) => {
+                let facts = HippoFacts::read_from(path.join(HIPPOFACTS_FILE_NAME))?;
+                let externals = match bindle_server {
+                    Some(url) => {
+                        let bindle_client = bindle::Client::new(ConnectionInfo {
+                            url: url.to_owned(),
+                            danger_accept_invalid_certs: profile.danger_accept_invalid_certs,
+                            api_key: None,
+                        });
+                        bindle_client.resolve_externals(&facts).await?
+                    }
+                    None if facts.has_external_handlers() => anyhow::bail!(
+                        "HIPPOFACTS references external bindles; specify --bindle-server to resolve them"
+                    ),
+                    None => Default::default(),
+                };
+                let build = builder::build(&facts, path, &externals)?;
+                build.write_to(dir)?;
+                println!(
+                    "Wrote {} to {} ({} parcel(s), {} to upload)",
+                    facts.bindle_id(),
+                    dir.display(),
+                    build.invoice.parcel.as_ref().map_or(0, |p| p.len()),
+                    build.sources.len()
+                );
             }
 
             Commands::Certificate(CertificateCommands::Add {
@@ -129,100 +261,715 @@ impl Cli {
                 public_key_path,
                 private_key_path,
             }) => {
-                // open files and read their contents
-                let public_key = fs::read_to_string(public_key_path)?;
-                let private_key = fs::read_to_string(private_key_path)?;
+                let (public_key, private_key) =
+                    read_certificate(public_key_path, private_key_path, &[])?;
                 let id = hippo_client
                     .add_certificate(name.to_owned(), public_key, private_key)
                     .await?;
                 println!("Added {} (ID = '{}')", name, id);
-                println!("IMPORTANT: save this Certificate ID for later - you will need it to update and/or delete the Certificate");
             }
 
-            Commands::Certificate(CertificateCommands::List {}) => {
-                let certificates = hippo_client.list_certificates().await?;
-                println!("{}", serde_json::to_string_pretty(&certificates.items)?);
+            Commands::Certificate(CertificateCommands::Get { certificate }) => {
+                let id = hippo_client.resolve_certificate(certificate).await?;
+                let mut certificate = hippo_client.get_certificate(&id).await?;
+                if !self.reveal {
+                    output::mask_certificate(&mut certificate);
+                }
+                output::print_item(&certificate, &certificate.id, self.output, || {
+                    describe::certificate(&certificate)
+                })?;
             }
 
-            Commands::Certificate(CertificateCommands::Remove { id }) => {
-                hippo_client.remove_certificate(id.to_owned()).await?;
-                println!("Removed {}", id);
+            Commands::Certificate(CertificateCommands::List { paging, search }) => {
+                let options = search.options(paging);
+                let mut certificates: Vec<_> = if paging.all {
+                    hippo_client.certificates(options).try_collect().await?
+                } else {
+                    output::page_items(hippo_client.list_certificates_page(&options).await?)
+                };
+                if !self.reveal {
+                    for certificate in &mut certificates {
+                        output::mask_certificate(certificate);
+                    }
+                }
+                output::print_list(&certificates, self.output)?;
+            }
+
+            Commands::Certificate(CertificateCommands::Expiring { within }) => {
+                let deadline = chrono::Utc::now() + *within;
+                let mut expiries: Vec<_> = hippo_client
+                    .list_all_certificates()
+                    .await?
+                    .into_iter()
+                    .map(|c| {
+                        let not_after = match tls::inspect_certificate(&c.public_key) {
+                            Ok(info) => Some(info.not_after),
+                            Err(e) => {
+                                eprintln!("warning: cannot read certificate {}: {}", c.name, e);
+                                None
+                            }
+                        };
+                        output::CertificateExpiry {
+                            not_after,
+                            channels: c.channels.iter().map(describe::qualified_name).collect(),
+                            name: c.name,
+                            id: c.id,
+                        }
+                    })
+                    .collect();
+                // soonest first, with any we could not read at the end
+                expiries.sort_by_key(|e| (e.not_after.is_none(), e.not_after));
+                output::print_list(&expiries, self.output)?;
+
+                let expiring = expiries
+                    .iter()
+                    .filter(|e| e.not_after.is_some_and(|t| t <= deadline))
+                    .count();
+                if expiring > 0 {
+                    anyhow::bail!(
+                        "{} certificate(s) expire before {}",
+                        expiring,
+                        deadline.format("%Y-%m-%d %H:%M:%S UTC")
+                    );
+                }
+            }
+
+            Commands::Certificate(CertificateCommands::Update {
+                certificate,
+                name,
+                public_key,
+                private_key,
+            }) => {
+                if name.is_none() && public_key.is_none() {
+                    anyhow::bail!("nothing to update; specify a new name or new keys");
+                }
+                let id = hippo_client.resolve_certificate(certificate).await?;
+                let current = hippo_client.get_certificate(&id).await?;
+                let (public_key, private_key) = match (public_key, private_key) {
+                    (Some(public_key_path), Some(private_key_path)) => {
+                        let domains: Vec<_> =
+                            current.channels.iter().map(|c| c.domain.as_str()).collect();
+                        read_certificate(public_key_path, private_key_path, &domains)?
+                    }
+                    _ => (current.public_key, current.private_key),
+                };
+                hippo_client
+                    .update_certificate(
+                        id,
+                        name.clone().unwrap_or(current.name),
+                        public_key,
+                        private_key,
+                    )
+                    .await?;
+                println!("Updated {}", certificate);
+            }
+
+            Commands::Certificate(CertificateCommands::Rotate {
+                certificate,
+                public_key_path,
+                private_key_path,
+                name,
+                remove_old,
+            }) => {
+                let old_id = hippo_client.resolve_certificate(certificate).await?;
+                let old = hippo_client.get_certificate(&old_id).await?;
+                let channels: Vec<_> = hippo_client
+                    .list_all_channels()
+                    .await?
+                    .into_iter()
+                    .filter(|c| c.certificate.as_ref().map(|c| &c.id) == Some(&old.id))
+                    .collect();
+                let domains: Vec<_> = channels.iter().map(|c| c.domain.as_str()).collect();
+                let (public_key, private_key) =
+                    read_certificate(public_key_path, private_key_path, &domains)?;
+                let name = match name {
+                    Some(n) => n.to_owned(),
+                    None => format!("{}-{}", old.name, chrono::Utc::now().format("%Y%m%d")),
+                };
+                let new_id = hippo_client
+                    .add_certificate(name.clone(), public_key, private_key)
+                    .await?;
+                println!("Added {} (ID = '{}')", name, new_id);
+
+                for channel in &channels {
+                    hippo_client
+                        .patch_channel(
+                            &channel.id,
+                            PatchChannelCommand {
+                                certificate_id: Some(Box::new(GuidNullableField {
+                                    value: Some(new_id.clone()),
+                                })),
+                                ..Default::default()
+                            },
+                        )
+                        .await?;
+                    println!("Moved {} to {}", describe::qualified_name(channel), name);
+                }
+
+                if *remove_old {
+                    hippo_client.remove_certificate(old.id).await?;
+                    println!("Removed {}", old.name);
+                }
+            }
+
+            Commands::Certificate(CertificateCommands::Remove { certificate }) => {
+                let id = hippo_client.resolve_certificate(certificate).await?;
+                hippo_client.remove_certificate(id).await?;
+                println!("Removed {}", certificate);
             }
 
             Commands::Channel(ChannelCommands::Add {
-                app_id,
+                app,
                 name,
                 domain,
                 range_rule,
                 revision_id,
                 certificate_id,
             }) => {
-                if range_rule.is_some() && revision_id.is_some() {
-                    anyhow::bail!("cannot specify both a range rule and a revision ID");
-                }
-                let revision_selection_strategy = match (range_rule, revision_id) {
-                    (Some(_), None) => ChannelRevisionSelectionStrategy::UseRangeRule,
-                    (None, Some(_)) => ChannelRevisionSelectionStrategy::UseSpecifiedRevision,
-                    _ => ChannelRevisionSelectionStrategy::UseRangeRule,
+                let revision_selection_strategy =
+                    revision_selection_strategy(range_rule, revision_id)?
+                        .unwrap_or(ChannelRevisionSelectionStrategy::UseRangeRule);
+                let app_id = hippo_client.resolve_app(app).await?;
+                let revision_id = match revision_id {
+                    Some(r) => Some(hippo_client.resolve_revision(&app_id, r).await?),
+                    None => None,
                 };
+                let certificate_id = match certificate_id {
+                    Some(c) => Some(hippo_client.resolve_certificate(c).await?),
+                    None => None,
+                };
+                if let (Some(certificate_id), Some(domain)) = (&certificate_id, domain) {
+                    check_certificate_binding(hippo_client, certificate_id, domain).await?;
+                }
                 let id = hippo_client
                     .add_channel(
-                        app_id.to_owned(),
+                        app_id,
                         name.to_owned(),
                         domain.to_owned(),
                         revision_selection_strategy,
                         range_rule.to_owned(),
-                        revision_id.to_owned(),
-                        certificate_id.to_owned(),
+                        revision_id,
+                        certificate_id,
                     )
                     .await?;
                 println!("Added {} (ID = '{}')", name, id);
-                println!("IMPORTANT: save this Channel ID for later - you will need it to update and/or delete the Channel");
             }
 
-            Commands::Channel(ChannelCommands::List {}) => {
-                let channels = hippo_client.list_channels().await?;
-                println!("{}", serde_json::to_string_pretty(&channels.items)?);
+            Commands::Channel(ChannelCommands::Get { channel }) => {
+                let id = hippo_client.resolve_channel(channel).await?;
+                let mut channel = hippo_client.get_channel_by_id(&id).await?;
+                if !self.reveal {
+                    output::mask_channel(&mut channel);
+                }
+                let status = match hippo_client.get_channel_status(&id).await {
+                    Ok(s) => Some(s),
+                    // older servers do not report channel status
+                    Err(e) if e.is::<NotFound>() => None,
+                    Err(e) => return Err(e),
+                };
+                output::print_item(&channel, &channel.id, self.output, || {
+                    describe::channel(&channel, status.as_ref())
+                })?;
+            }
+
+            Commands::Channel(ChannelCommands::List {
+                app,
+                paging,
+                search,
+            }) => {
+                let options = search.options(paging);
+                let mut channels = match app {
+                    // the API cannot filter by app, so look through every page
+                    Some(app) => {
+                        let app_id = hippo_client.resolve_app(app).await?;
+                        hippo_client
+                            .channels(options)
+                            .try_filter(|c| future::ready(c.app_id == app_id))
+                            .try_collect()
+                            .await?
+                    }
+                    None if paging.all => hippo_client.channels(options).try_collect().await?,
+                    None => output::page_items(hippo_client.list_channels_page(&options).await?),
+                };
+                if !self.reveal {
+                    output::mask_channels(&mut channels);
+                }
+                output::print_list(&channels, self.output)?;
+            }
+
+            Commands::Channel(ChannelCommands::Update {
+                channel,
+                domain,
+                range_rule,
+                revision_id,
+                certificate_id,
+                clear_certificate,
+            }) => {
+                let revision_selection_strategy =
+                    revision_selection_strategy(range_rule, revision_id)?;
+                let id = hippo_client.resolve_channel(channel).await?;
+                let active_revision_id = match revision_id {
+                    Some(r) => {
+                        let app_id = hippo_client.get_channel_by_id(&id).await?.app_id;
+                        Some(hippo_client.resolve_revision(&app_id, r).await?)
+                    }
+                    None => None,
+                };
+                let certificate_id = match certificate_id {
+                    Some(c) => Some(hippo_client.resolve_certificate(c).await?),
+                    None => None,
+                };
+                if !clear_certificate && (certificate_id.is_some() || domain.is_some()) {
+                    let current = hippo_client.get_channel_by_id(&id).await?;
+                    let bound = certificate_id
+                        .clone()
+                        .or_else(|| current.certificate.as_ref().map(|c| c.id.clone()));
+                    if let Some(bound) = bound {
+                        let domain = domain.as_deref().unwrap_or(&current.domain);
+                        check_certificate_binding(hippo_client, &bound, domain).await?;
+                    }
+                }
+                let command = PatchChannelCommand {
+                    domain: domain.as_ref().map(|d| {
+                        Box::new(StringField {
+                            value: Some(d.to_owned()),
+                        })
+                    }),
+                    revision_selection_strategy: revision_selection_strategy.map(|s| {
+                        Box::new(ChannelRevisionSelectionStrategyField { value: Some(s) })
+                    }),
+                    range_rule: range_rule.as_ref().map(|r| {
+                        Box::new(StringField {
+                            value: Some(r.to_owned()),
+                        })
+                    }),
+                    active_revision_id: active_revision_id
+                        .map(|r| Box::new(GuidNullableField { value: Some(r) })),
+                    certificate_id: if *clear_certificate {
+                        Some(Box::new(GuidNullableField { value: None }))
+                    } else {
+                        certificate_id.map(|c| Box::new(GuidNullableField { value: Some(c) }))
+                    },
+                    ..Default::default()
+                };
+                if command == PatchChannelCommand::default() {
+                    anyhow::bail!(
+                        "nothing to update; specify at least one of the channel's settings"
+                    );
+                }
+                hippo_client.patch_channel(&id, command).await?;
+                println!("Updated {}", channel);
+            }
+
+            Commands::Channel(ChannelCommands::Promote {
+                from,
+                to,
+                range_rule,
+                yes,
+            }) => {
+                let source = hippo_client
+                    .get_channel_by_id(&hippo_client.resolve_channel(from).await?)
+                    .await?;
+                let target = hippo_client
+                    .get_channel_by_id(&hippo_client.resolve_channel(to).await?)
+                    .await?;
+                if source.app_id != target.app_id {
+                    anyhow::bail!(
+                        "cannot promote between {} and {}, as they belong to different apps",
+                        from,
+                        to
+                    );
+                }
+                let revision = source
+                    .active_revision
+                    .as_deref()
+                    .ok_or_else(|| anyhow::anyhow!("{} has no active revision to promote", from))?;
+
+                let mut promoted = target.clone();
+                promoted.active_revision = Some(Box::new(revision.clone()));
+                let command = if *range_rule {
+                    let rule = format!("={}", revision.revision_number);
+                    promoted.revision_selection_strategy =
+                        ChannelRevisionSelectionStrategy::UseRangeRule;
+                    promoted.range_rule = Some(rule.clone());
+                    PatchChannelCommand {
+                        revision_selection_strategy: Some(Box::new(
+                            ChannelRevisionSelectionStrategyField {
+                                value: Some(ChannelRevisionSelectionStrategy::UseRangeRule),
+                            },
+                        )),
+                        range_rule: Some(Box::new(StringField { value: Some(rule) })),
+                        ..Default::default()
+                    }
+                } else {
+                    promoted.revision_selection_strategy =
+                        ChannelRevisionSelectionStrategy::UseSpecifiedRevision;
+                    pin_revision(&revision.id)
+                };
+
+                let changes = describe::channel_changes(&target, &promoted);
+                if changes.is_empty() {
+                    println!("{} is already running {}", to, revision.revision_number);
+                    return Ok(());
+                }
+                println!(
+                    "Promoting {} from {} to {}:",
+                    revision.revision_number, from, to
+                );
+                output::print_changes(&changes);
+                if !yes {
+                    if !session::is_interactive() {
+                        anyhow::bail!("pass --yes to promote without confirmation");
+                    }
+                    if !Confirm::new()
+                        .with_prompt(format!("Deploy {} to {}?", revision.revision_number, to))
+                        .default(false)
+                        .interact()?
+                    {
+                        println!("Promotion cancelled");
+                        return Ok(());
+                    }
+                }
+                hippo_client.patch_channel(&target.id, command).await?;
+                println!("Promoted {} to {}", revision.revision_number, to);
             }
 
-            Commands::Channel(ChannelCommands::Remove { id }) => {
-                hippo_client.remove_channel(id.to_owned()).await?;
-                println!("Removed {}", id);
+            Commands::Channel(ChannelCommands::Rollback { channel, to }) => {
+                let current = hippo_client
+                    .get_channel_by_id(&hippo_client.resolve_channel(channel).await?)
+                    .await?;
+                let revision = match to {
+                    Some(r) => {
+                        let id = hippo_client.resolve_revision(&current.app_id, r).await?;
+                        hippo_client.get_revision(&id).await?
+                    }
+                    None => {
+                        let active = current.active_revision.as_deref().ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "{} has no active revision; use --to to choose one",
+                                channel
+                            )
+                        })?;
+                        let active_version = semver::Version::parse(&active.revision_number)
+                            .map_err(|_| {
+                                anyhow::anyhow!(
+                                    "cannot find the revision before {}, as it is not a semantic version; use --to to choose one",
+                                    active.revision_number
+                                )
+                            })?;
+                        // revision numbers that are not versions cannot be ordered, so are never rolled back to
+                        let revisions: Vec<_> = hippo_client
+                            .list_all_revisions()
+                            .await?
+                            .into_iter()
+                            .filter(|r| r.app_id == current.app_id)
+                            .filter_map(|r| {
+                                semver::Version::parse(&r.revision_number)
+                                    .ok()
+                                    .map(|v| (v, r))
+                            })
+                            .collect();
+                        let previous = versioning::previous(
+                            &active_version,
+                            revisions.iter().map(|(v, _)| v.clone()),
+                        )
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "{} has no revision older than {}",
+                                channel,
+                                active.revision_number
+                            )
+                        })?;
+                        revisions
+                            .into_iter()
+                            .find(|(v, _)| *v == previous)
+                            .unwrap()
+                            .1
+                    }
+                };
+
+                let mut rolled_back = current.clone();
+                rolled_back.revision_selection_strategy =
+                    ChannelRevisionSelectionStrategy::UseSpecifiedRevision;
+                rolled_back.active_revision = Some(Box::new(revision.clone()));
+                let changes = describe::channel_changes(&current, &rolled_back);
+                if changes.is_empty() {
+                    println!(
+                        "{} is already running {}",
+                        channel, revision.revision_number
+                    );
+                    return Ok(());
+                }
+                hippo_client
+                    .patch_channel(&current.id, pin_revision(&revision.id))
+                    .await?;
+                println!("Rolled back {} to {}:", channel, revision.revision_number);
+                output::print_changes(&changes);
+            }
+
+            Commands::Channel(ChannelCommands::Remove { channel }) => {
+                let id = hippo_client.resolve_channel(channel).await?;
+                hippo_client.remove_channel(id).await?;
+                println!("Removed {}", channel);
             }
 
-            Commands::Channel(ChannelCommands::Logs { id }) => {
-                for line in hippo_client.channel_logs(id.to_owned()).await?.logs {
+            Commands::Channel(ChannelCommands::Logs {
+                channel,
+                follow,
+                tail,
+                since,
+                grep,
+            }) => {
+                let id = hippo_client.resolve_channel(channel).await?;
+                let mut filter =
+                    logs::LogFilter::new(since.map(|s| chrono::Utc::now() - s), grep.clone());
+                let mut seen = hippo_client.channel_logs(id.clone()).await?.logs;
+                let lines = filter.select(&seen);
+                let skip = tail.map_or(0, |n| lines.len().saturating_sub(n));
+                for line in &lines[skip..] {
                     println!("{}", line);
                 }
+
+                if *follow {
+                    loop {
+                        tokio::time::sleep(std::time::Duration::from_secs(LOG_POLL_SECONDS)).await;
+                        let current = match hippo_client.channel_logs(id.clone()).await {
+                            Ok(l) => l.logs,
+                            Err(e) if e.is::<Unauthorized>() => return Err(e),
+                            // keep following through brief outages
+                            Err(e) => {
+                                eprintln!("warning: cannot fetch logs: {}", e);
+                                continue;
+                            }
+                        };
+                        for line in filter.select(logs::new_lines(&seen, &current)) {
+                            println!("{}", line);
+                        }
+                        seen = current;
+                    }
+                }
             }
 
             Commands::Env(EnvCommands::Add {
                 key,
                 value,
-                channel_id,
+                channel,
+                source,
             }) => {
+                let value = variable_value(key, value, source)?;
+                let channel_id = hippo_client.resolve_channel(channel).await?;
                 hippo_client
-                    .add_environment_variable(
-                        key.to_owned(),
-                        value.to_owned(),
-                        channel_id.to_owned(),
-                    )
+                    .add_environment_variable(key.to_owned(), value, channel_id)
                     .await?;
-                println!("Added {}={}", key, value);
+                println!("Added {}", key);
             }
 
-            Commands::Env(EnvCommands::List { channel_id }) => {
-                let envs = hippo_client
-                    .list_environment_variables(channel_id.to_owned())
+            Commands::Env(EnvCommands::Import {
+                channel,
+                file,
+                replace,
+            }) => {
+                let imported = read_variables_file(file)?;
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                let existing = channel_variable_pairs(hippo_client, &channel_id).await?;
+
+                // keep the channel's order, with new keys after the existing ones
+                let mut variables = if *replace { vec![] } else { existing.clone() };
+                for (key, value) in imported {
+                    client::upsert_variable(&mut variables, key, value);
+                }
+
+                let changes = describe::variable_changes(&existing, &variables);
+                if changes.is_empty() {
+                    println!("{} is already up to date", channel);
+                    return Ok(());
+                }
+                hippo_client
+                    .set_environment_variables(&channel_id, variables)
                     .await?;
-                println!("{}", serde_json::to_string_pretty(&envs)?);
+                println!("Updated {}: {}", channel, change_summary(&changes));
             }
 
-            Commands::Env(EnvCommands::Remove { channel_id, id }) => {
+            Commands::Env(EnvCommands::Copy {
+                from,
+                to,
+                keys,
+                overwrite,
+            }) => {
+                let from_id = hippo_client.resolve_channel(from).await?;
+                let to_id = hippo_client.resolve_channel(to).await?;
+                let mut source = vec![];
+                for (key, value) in channel_variable_pairs(hippo_client, &from_id).await? {
+                    client::upsert_variable(&mut source, key, value);
+                }
+                if !keys.is_empty() {
+                    if let Some(missing) =
+                        keys.iter().find(|k| !source.iter().any(|(s, _)| s == *k))
+                    {
+                        anyhow::bail!("{} has no environment variable {}", from, missing);
+                    }
+                    source.retain(|(k, _)| keys.contains(k));
+                }
+
+                let existing = channel_variable_pairs(hippo_client, &to_id).await?;
+                let mut variables = existing.clone();
+                let mut kept = vec![];
+                for (key, value) in source {
+                    if !overwrite && existing.iter().any(|(k, v)| *k == key && *v != value) {
+                        kept.push(key);
+                    } else {
+                        client::upsert_variable(&mut variables, key, value);
+                    }
+                }
+                if !kept.is_empty() {
+                    eprintln!(
+                        "Kept the existing values of {} in {}; pass --overwrite to replace them",
+                        kept.join(", "),
+                        to
+                    );
+                }
+
+                let changes = describe::variable_changes(&existing, &variables);
+                if changes.is_empty() {
+                    println!("{} is already up to date", to);
+                    return Ok(());
+                }
+                output::print_variable_changes(&changes, self.reveal);
                 hippo_client
-                    .remove_environment_variable(channel_id.to_owned(), id.to_owned())
+                    .set_environment_variables(&to_id, variables)
+                    .await?;
+                println!("Updated {}: {}", to, change_summary(&changes));
+            }
+
+            Commands::Env(EnvCommands::Diff { channel, file }) => {
+                let wanted = read_variables_file(file)?;
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                let existing = channel_variable_pairs(hippo_client, &channel_id).await?;
+                let changes = describe::variable_changes(&existing, &wanted);
+                if changes.is_empty() {
+                    println!("{} matches {}", channel, file.display());
+                } else {
+                    output::print_variable_changes(&changes, self.reveal);
+                    anyhow::bail!(
+                        "{} differs from {}: {}",
+                        channel,
+                        file.display(),
+                        change_summary(&changes)
+                    );
+                }
+            }
+
+            Commands::Env(EnvCommands::Apply { channel, file, yes }) => {
+                let wanted = read_variables_file(file)?;
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                let existing = channel_variable_pairs(hippo_client, &channel_id).await?;
+                let changes = describe::variable_changes(&existing, &wanted);
+                if changes.is_empty() {
+                    println!("{} already matches {}", channel, file.display());
+                    return Ok(());
+                }
+                println!("Applying {} to {}:", file.display(), channel);
+                output::print_variable_changes(&changes, self.reveal);
+                if !yes {
+                    if !session::is_interactive() {
+                        anyhow::bail!("pass --yes to apply without confirmation");
+                    }
+                    if !Confirm::new()
+                        .with_prompt(format!("Update {}?", channel))
+                        .default(false)
+                        .interact()?
+                    {
+                        println!("Apply cancelled");
+                        return Ok(());
+                    }
+                }
+                hippo_client
+                    .set_environment_variables(&channel_id, wanted)
+                    .await?;
+                println!("Updated {}: {}", channel, change_summary(&changes));
+            }
+
+            Commands::Env(EnvCommands::Export { channel }) => {
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                // a file with a key twice could not be imported again, so keep
+                // only the last value of any duplicates
+                let mut variables = vec![];
+                let mut envs = channel_variables(hippo_client, &channel_id).await?;
+                if !self.reveal {
+                    eprintln!("Values are masked; pass --reveal to export them");
+                    output::mask_variables(&mut envs);
+                }
+                for e in envs {
+                    client::upsert_variable(&mut variables, e.key, e.value);
+                }
+                print!("{}", dotenv::format(&variables));
+            }
+
+            Commands::Env(EnvCommands::List { channel }) => {
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                let mut envs = channel_variables(hippo_client, &channel_id).await?;
+                if !self.reveal {
+                    output::mask_variables(&mut envs);
+                }
+                output::print_list(&envs, self.output)?;
+            }
+
+            Commands::Env(EnvCommands::Set {
+                key,
+                value,
+                channel,
+                source,
+            }) => {
+                let value = variable_value(key, value, source)?;
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                let replaced = hippo_client
+                    .set_environment_variable(key.to_owned(), value, channel_id)
                     .await?;
-                println!("Removed {}", id);
+                if replaced {
+                    println!("Updated {}", key);
+                } else {
+                    println!("Added {}", key);
+                }
+            }
+
+            Commands::Env(EnvCommands::Unset {
+                channel,
+                key,
+                ignore_missing,
+            }) => {
+                let channel_id = hippo_client.resolve_channel(channel).await?;
+                match hippo_client
+                    .remove_environment_variable(channel_id, key.to_owned())
+                    .await
+                {
+                    Ok(()) => println!("Removed {}", key),
+                    Err(e) if *ignore_missing && e.is::<VariableNotSet>() => {
+                        println!("{} is not set", key)
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+
+            Commands::Facts(FactsCommands::Check { path }) => {
+                let (facts_path, base_dir) = if path.is_dir() {
+                    (path.join(HIPPOFACTS_FILE_NAME), path.clone())
+                } else {
+                    let base_dir = path.parent().map(PathBuf::from).unwrap_or_default();
+                    (path.clone(), base_dir)
+                };
+                let text = fs::read_to_string(&facts_path)
+                    .map_err(|e| anyhow::anyhow!("cannot read {}: {}", facts_path.display(), e))?;
+                let errors = match HippoFacts::parse(&text) {
+                    Ok(facts) => facts.check_files(&base_dir),
+                    Err(errors) => errors,
+                };
+                if !errors.is_empty() {
+                    for e in &errors {
+                        eprintln!("{}: {}", facts_path.display(), e);
+                    }
+                    anyhow::bail!("{} has {} error(s)", facts_path.display(), errors.len());
+                }
+                println!("{} is valid", facts_path.display());
             }
 
             Commands::Login {
@@ -230,6 +977,7 @@ impl Cli {
                 username,
                 password,
                 danger_accept_invalid_certs,
+                credential_helper,
             } => {
                 let h_username: String = match username {
                     Some(u) => u.to_owned(),
@@ -245,26 +993,144 @@ impl Cli {
                     api_key: None,
                 });
                 let token = hippo_client.login(h_username.clone(), h_password).await?;
-                hippo_conf.danger_accept_invalid_certs = *danger_accept_invalid_certs;
-                hippo_conf.username = h_username;
-                hippo_conf.url = url.to_owned();
-                hippo_conf.token_info = Some(token);
-                if !hippo_config_path.exists() && hippo_config_path.ancestors().count() != 0 {
-                    fs::create_dir_all(hippo_config_path.parent().unwrap())?;
+                if let Some(old) = hippo_conf.profiles.get_mut(profile_name) {
+                    if old.credential_helper != *credential_helper {
+                        old.erase_token(hippo_config_path)?;
+                    }
+                }
+                let mut new_profile = Profile {
+                    danger_accept_invalid_certs: *danger_accept_invalid_certs,
+                    username: h_username.clone(),
+                    url: url.to_owned(),
+                    credential_helper: credential_helper.to_owned(),
+                    ..Default::default()
+                };
+                new_profile.store_token(profile_name, hippo_config_path, token)?;
+                hippo_conf
+                    .profiles
+                    .insert(profile_name.to_owned(), new_profile);
+                if hippo_conf.current_profile.is_none() {
+                    hippo_conf.current_profile = Some(profile_name.to_owned());
                 }
-                serde_json::to_writer(File::create(hippo_config_path)?, &hippo_conf)?;
-                println!("Logged in as {}", hippo_conf.username);
+                hippo_conf.save(hippo_config_path)?;
+                println!("Logged in as {} (profile '{}')", h_username, profile_name);
             }
 
             Commands::Logout {} => {
-                hippo_conf = Default::default();
-                if !hippo_config_path.exists() && hippo_config_path.ancestors().count() != 0 {
-                    fs::create_dir_all(hippo_config_path.parent().unwrap())?;
+                if let Some(p) = hippo_conf.profiles.get_mut(profile_name) {
+                    p.erase_token(hippo_config_path)?;
+                    p.username = "".to_owned();
                 }
-                serde_json::to_writer(File::create(hippo_config_path)?, &hippo_conf)?;
+                hippo_conf.save(hippo_config_path)?;
                 println!("Logged out");
             }
 
+            Commands::Profile(ProfileCommands::List {}) => {
+                for (name, p) in &hippo_conf.profiles {
+                    let marker = if *name == profile_name { "*" } else { " " };
+                    let user = if p.username.is_empty() {
+                        "(logged out)"
+                    } else {
+                        &p.username
+                    };
+                    println!("{} {}\t{}\t{}", marker, name, p.url, user);
+                }
+            }
+
+            Commands::Profile(ProfileCommands::Use { name }) => {
+                if !hippo_conf.profiles.contains_key(name) {
+                    anyhow::bail!("profile '{}' does not exist", name);
+                }
+                hippo_conf.current_profile = Some(name.to_owned());
+                hippo_conf.save(hippo_config_path)?;
+                println!("Switched to profile '{}'", name);
+            }
+
+            Commands::Profile(ProfileCommands::Remove { name }) => {
+                let mut removed = hippo_conf
+                    .profiles
+                    .remove(name)
+                    .ok_or_else(|| anyhow::anyhow!("profile '{}' does not exist", name))?;
+                removed.erase_token(hippo_config_path)?;
+                if hippo_conf.current_profile.as_deref() == Some(name) {
+                    hippo_conf.current_profile = None;
+                }
+                hippo_conf.save(hippo_config_path)?;
+                println!("Removed profile '{}'", name);
+            }
+
+            Commands::Profile(ProfileCommands::Show { name }) => {
+                let name = name.as_deref().unwrap_or(profile_name);
+                let p = hippo_conf
+                    .profiles
+                    .get(name)
+                    .ok_or_else(|| anyhow::anyhow!("profile '{}' does not exist", name))?;
+                println!("Profile:   {}", name);
+                println!("URL:       {}", p.url);
+                println!("Username:  {}", p.username);
+                println!("Logged in: {}", p.credential.is_some());
+                println!(
+                    "Token:     {}",
+                    p.credential_store(hippo_config_path).describe()
+                );
+                if p.danger_accept_invalid_certs {
+                    println!("WARNING: invalid TLS certificates are accepted for this profile");
+                }
+            }
+
+            Commands::Push {
+                path,
+                bindle_server,
+                version_strategy,
+            } => {
+                let mut facts = HippoFacts::read_from(path.join(HIPPOFACTS_FILE_NAME))?;
+                facts.bindle.version = match version_strategy {
+                    VersionStrategy::Exact => facts.bindle.version,
+                    VersionStrategy::Prerelease => {
+                        let user = match profile.username.as_str() {
+                            "" => std::env::var("USER")
+                                .or_else(|_| std::env::var("USERNAME"))
+                                .ok(),
+                            u => Some(u.to_owned()),
+                        };
+                        versioning::prerelease(
+                            &facts.bindle.version,
+                            user.as_deref(),
+                            chrono::Utc::now(),
+                            versioning::git_short_commit(path).as_deref(),
+                        )
+                    }
+                    VersionStrategy::BumpPatch => {
+                        let apps = hippo_client.list_all_apps().await?;
+                        let existing = match apps.iter().find(|a| a.storage_id == facts.bindle.name)
+                        {
+                            Some(app) => hippo_client
+                                .list_all_revisions()
+                                .await?
+                                .into_iter()
+                                .filter(|r| r.app_id == app.id)
+                                .filter_map(|r| semver::Version::parse(&r.revision_number).ok())
+                                .collect(),
+                            None => vec![],
+                        };
+                        versioning::bump_patch(&facts.bindle.version, existing)
+                    }
+                };
+                let bindle_client = bindle::Client::new(ConnectionInfo {
+                    url: bindle_server.to_owned(),
+                    danger_accept_invalid_certs: profile.danger_accept_invalid_certs,
+                    api_key: None,
+                });
+                let externals = bindle_client.resolve_externals(&facts).await?;
+                let build = builder::build(&facts, path, &externals)?;
+                let summary = bindle_client.push(&build).await?;
+                println!("Pushed {}: {}", facts.bindle_id(), summary);
+                hippo_client
+                    .add_revision(facts.bindle.name, facts.bindle.version.to_string())
+                    .await?;
+                println!("Added revision {}", facts.bindle.version);
+            }
+
             Commands::Register {
                 url,
                 username,
@@ -301,16 +1167,310 @@ impl Cli {
                 println!("Added {}", revision_number);
             }
 
-            Commands::Revision(RevisionCommands::List {}) => {
-                let revisions = hippo_client.list_revisions().await?;
-                println!("{}", serde_json::to_string_pretty(&revisions.items)?);
+            Commands::Revision(RevisionCommands::Get { revision }) => {
+                let id = match revision.split_once('/') {
+                    Some((app, number)) => {
+                        let app_id = hippo_client.resolve_app(app).await?;
+                        hippo_client.resolve_revision(&app_id, number).await?
+                    }
+                    None => revision.to_owned(),
+                };
+                let revision = hippo_client.get_revision(&id).await?;
+                let app = match hippo_client.get_app(&revision.app_id).await {
+                    Ok(a) => Some(a),
+                    // the app may have been removed since
+                    Err(e) if e.is::<NotFound>() => None,
+                    Err(e) => return Err(e),
+                };
+                let channels: Vec<_> = hippo_client
+                    .list_all_channels()
+                    .await?
+                    .into_iter()
+                    .filter(|c| c.active_revision.as_ref().map(|r| &r.id) == Some(&revision.id))
+                    .collect();
+                output::print_item(&revision, &revision.id, self.output, || {
+                    describe::revision(&revision, app.as_ref(), &channels)
+                })?;
+            }
+
+            Commands::Revision(RevisionCommands::List { paging }) => {
+                let revisions = if paging.all {
+                    hippo_client
+                        .revisions(paging.options())
+                        .try_collect()
+                        .await?
+                } else {
+                    output::page_items(hippo_client.list_revisions_page(&paging.options()).await?)
+                };
+                output::print_list(&revisions, self.output)?;
             }
 
             Commands::Whoami {} => {
-                println!("{}", hippo_conf.username);
+                println!("{}", profile.username);
+                if let Some(expiry) = profile.token_expiry() {
+                    let verb = if expiry <= chrono::Utc::now() {
+                        "expired"
+                    } else {
+                        "expires"
+                    };
+                    println!(
+                        "Session {} {} ({})",
+                        verb,
+                        expiry.format("%Y-%m-%d %H:%M:%S UTC"),
+                        session::describe_expiry(expiry)
+                    );
+                }
             }
         }
 
         Ok(())
     }
 }
+
+/// The revision selection strategy implied by a channel's range rule and
+/// revision flags, if either was given
+fn revision_selection_strategy(
+    range_rule: &Option<String>,
+    revision_id: &Option<String>,
+) -> anyhow::Result<Option<ChannelRevisionSelectionStrategy>> {
+    match (range_rule, revision_id) {
+        (Some(_), Some(_)) => anyhow::bail!("cannot specify both a range rule and a revision ID"),
+        (Some(_), None) => Ok(Some(ChannelRevisionSelectionStrategy::UseRangeRule)),
+        (None, Some(_)) => Ok(Some(ChannelRevisionSelectionStrategy::UseSpecifiedRevision)),
+        (None, None) => Ok(None),
+    }
+}
+
+/// A patch that makes a channel run the given revision and only that revision
+fn pin_revision(revision_id: &str) -> PatchChannelCommand {
+    PatchChannelCommand {
+        revision_selection_strategy: Some(Box::new(ChannelRevisionSelectionStrategyField {
+            value: Some(ChannelRevisionSelectionStrategy::UseSpecifiedRevision),
+        })),
+        active_revision_id: Some(Box::new(GuidNullableField {
+            value: Some(revision_id.to_owned()),
+        })),
+        ..Default::default()
+    }
+}
+
+/// Reads a certificate's PEM files and checks that they are a matching pair,
+/// printing who the certificate is for and warning if it has expired or does
+/// not cover the given domains
+fn read_certificate(
+    public_key_path: &Path,
+    private_key_path: &Path,
+    domains: &[&str],
+) -> anyhow::Result<(String, String)> {
+    let public_key = fs::read_to_string(public_key_path)?;
+    let private_key = fs::read_to_string(private_key_path)?;
+    let info = tls::inspect(&public_key, &private_key)?;
+    output::print_fields(&[
+        ("Subject", info.subject.clone()),
+        ("Names", output::or_none(Some(&info.names.join(", ")))),
+        (
+            "Expires",
+            format!(
+                "{} ({})",
+                info.not_after.format("%Y-%m-%d %H:%M:%S UTC"),
+                session::describe_expiry(info.not_after)
+            ),
+        ),
+    ]);
+    warn_about_certificate(&info, domains);
+    Ok((public_key, private_key))
+}
+
+/// Warns if the certificate a channel is being bound to has expired or does not
+/// cover the channel's domain
+async fn check_certificate_binding(
+    hippo_client: &Client,
+    certificate_id: &str,
+    domain: &str,
+) -> anyhow::Result<()> {
+    let certificate = hippo_client.get_certificate(certificate_id).await?;
+    // certificates uploaded before hippo checked them may not parse; the server
+    // accepted them, so there is nothing to warn about here
+    if let Ok(info) = tls::inspect_certificate(&certificate.public_key) {
+        warn_about_certificate(&info, &[domain]);
+    }
+    Ok(())
+}
+
+fn warn_about_certificate(info: &tls::CertificateInfo, domains: &[&str]) {
+    for warning in &info.warnings {
+        eprintln!("warning: {}", warning);
+    }
+    if info.is_expired() {
+        eprintln!(
+            "warning: the certificate expired {}",
+            session::describe_expiry(info.not_after)
+        );
+    }
+    for domain in domains.iter().filter(|d| !d.is_empty() && !info.covers(d)) {
+        eprintln!(
+            "warning: the certificate does not cover {}; it is valid for {}",
+            domain,
+            output::or_none(Some(&info.names.join(", ")))
+        );
+    }
+}
+
+/// A channel's environment variables, warning about any key that is set more
+/// than once
+async fn channel_variables(
+    hippo_client: &Client,
+    channel_id: &str,
+) -> anyhow::Result<Vec<EnvironmentVariableItem>> {
+    let variables = hippo_client
+        .list_environment_variables(channel_id.to_owned())
+        .await?;
+    for key in duplicate_keys(&variables) {
+        eprintln!(
+            "warning: {} is set more than once on the channel; run 'hippo env set' to keep a single value",
+            key
+        );
+    }
+    Ok(variables)
+}
+
+/// The keys that appear more than once in a channel's environment variables,
+/// which versions of hippo before `env set` could create
+fn duplicate_keys(environment_variables: &[EnvironmentVariableItem]) -> Vec<&str> {
+    environment_variables
+        .iter()
+        .enumerate()
+        .filter(|(i, e)| {
+            environment_variables[..*i].iter().all(|p| p.key != e.key)
+                && environment_variables[i + 1..]
+                    .iter()
+                    .any(|n| n.key == e.key)
+        })
+        .map(|(_, e)| e.key.as_str())
+        .collect()
+}
+
+/// An environment variable's value from the command line, a file, stdin or a
+/// prompt that does not echo it
+fn variable_value(
+    key: &str,
+    value: &Option<String>,
+    source: &ValueSource,
+) -> anyhow::Result<String> {
+    if let Some(value) = value {
+        return Ok(value.to_owned());
+    }
+    let text = match &source.value_from_file {
+        Some(path) if path == Path::new("-") => read_stdin()?,
+        Some(path) => fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?,
+        None if session::is_interactive() => {
+            return Ok(Password::new()
+                .with_prompt(format!("Enter value for {}", key))
+                .interact()?)
+        }
+        None => read_stdin()?,
+    };
+    // files and piped input usually end with a newline that is not part of the value
+    let value = text.strip_suffix('\n').unwrap_or(&text);
+    Ok(value.strip_suffix('\r').unwrap_or(value).to_owned())
+}
+
+/// A client that connects to the profile's server with its saved token
+fn profile_client(profile: &Profile) -> Client {
+    Client::new(ConnectionInfo {
+        url: profile.url.clone(),
+        danger_accept_invalid_certs: profile.danger_accept_invalid_certs,
+        api_key: profile.token_info.as_ref().and_then(|t| t.token.clone()),
+    })
+}
+
+fn read_stdin() -> anyhow::Result<String> {
+    let mut text = String::new();
+    std::io::stdin().read_to_string(&mut text)?;
+    Ok(text)
+}
+
+async fn channel_variable_pairs(
+    hippo_client: &Client,
+    channel_id: &str,
+) -> anyhow::Result<Vec<(String, String)>> {
+    Ok(channel_variables(hippo_client, channel_id)
+        .await?
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect())
+}
+
+/// Reads environment variables from a file: a TOML table of keys and values if
+/// its name ends in `.toml`, and a dotenv file otherwise
+fn read_variables_file(path: &Path) -> anyhow::Result<Vec<(String, String)>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+    let variables = if path.extension().is_some_and(|e| e == "toml") {
+        let table: toml::value::Table =
+            toml::from_str(&text).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?;
+        table
+            .into_iter()
+            .map(|(key, value)| match value {
+                toml::Value::String(s) => Ok((key, s)),
+                toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                    Ok((key, value.to_string()))
+                }
+                _ => Err(anyhow::anyhow!(
+                    "{}: {} must be a string, number or boolean",
+                    path.display(),
+                    key
+                )),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    } else {
+        dotenv::parse(&text).map_err(|e| anyhow::anyhow!("{}: {}", path.display(), e))?
+    };
+    if let Some((key, _)) = variables.iter().find(|(_, v)| v == output::MASKED_VALUE) {
+        anyhow::bail!(
+            "{}: {} has a masked value; export the variables again with --reveal",
+            path.display(),
+            key
+        );
+    }
+    Ok(variables)
+}
+
+fn change_summary(changes: &[(String, Option<String>, Option<String>)]) -> String {
+    let count = |added: bool, removed: bool| {
+        changes
+            .iter()
+            .filter(|(_, before, after)| before.is_none() == added && after.is_none() == removed)
+            .count()
+    };
+    format!(
+        "{} added, {} changed, {} removed",
+        count(true, false),
+        count(false, false),
+        count(false, true)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(keys: &[&str]) -> Vec<EnvironmentVariableItem> {
+        keys.iter()
+            .map(|k| EnvironmentVariableItem::new("channel".into(), k.to_string(), "v".into()))
+            .collect()
+    }
+
+    #[test]
+    fn duplicate_keys_are_listed_once_in_order() {
+        let variables = variables(&["B", "A", "C", "A", "B", "A"]);
+        assert_eq!(duplicate_keys(&variables), vec!["B", "A"]);
+    }
+
+    #[test]
+    fn unique_keys_have_no_duplicates() {
+        assert!(duplicate_keys(&variables(&["A", "B", "C"])).is_empty());
+        assert!(duplicate_keys(&[]).is_empty());
+    }
+}
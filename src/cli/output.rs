@@ -0,0 +1,405 @@
+use chrono::{DateTime, Utc};
+use colored::Colorize;
+use hippo_openapi::models::{
+    AppItem, CertificateItem, ChannelItem, EnvironmentVariableItem, RevisionItem,
+};
+use serde::Serialize;
+
+use super::session;
+use crate::client::Page;
+
+/// How list commands print their results
+#[derive(Clone, Copy, clap::ValueEnum)]
+pub(crate) enum OutputFormat {
+    /// Aligned columns for reading in a terminal
+    Table,
+    /// The items as JSON
+    Json,
+    /// The items as YAML
+    Yaml,
+    /// Like table, with extra columns
+    Wide,
+    /// Only the ID of each item, one per line, for piping into other commands
+    Name,
+}
+
+/// A resource that can be listed as a row of a table
+pub(crate) trait Tabular {
+    fn headers(wide: bool) -> Vec<&'static str>;
+    fn row(&self, wide: bool) -> Vec<String>;
+    /// What `--output name` prints: the value other commands accept to identify the item
+    fn id(&self) -> &str;
+}
+
+pub(crate) fn print_list<T: Tabular + Serialize>(
+    items: &[T],
+    format: OutputFormat,
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(items)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(items)?),
+        OutputFormat::Name => {
+            for item in items {
+                println!("{}", item.id());
+            }
+        }
+        OutputFormat::Table | OutputFormat::Wide => {
+            let wide = matches!(format, OutputFormat::Wide);
+            let rows: Vec<_> = items.iter().map(|i| i.row(wide)).collect();
+            print_table(&T::headers(wide), &rows, 0);
+        }
+    }
+    Ok(())
+}
+
+/// Prints a single item, calling `describe` to print it for a human reader
+pub(crate) fn print_item<T: Serialize>(
+    item: &T,
+    id: &str,
+    format: OutputFormat,
+    describe: impl FnOnce(),
+) -> anyhow::Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(item)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(item)?),
+        OutputFormat::Name => println!("{}", id),
+        OutputFormat::Table | OutputFormat::Wide => describe(),
+    }
+    Ok(())
+}
+
+/// Prints `label: value` lines with the values lined up
+pub(crate) fn print_fields(fields: &[(&str, String)]) {
+    let width = fields.iter().map(|(l, _)| l.len() + 1).max().unwrap_or(0);
+    for (label, value) in fields {
+        println!("{:<w$} {}", format!("{}:", label), value, w = width);
+    }
+}
+
+/// The items on a page, after noting on stderr if there are more pages, so
+/// as not to disturb machine-readable output
+pub(crate) fn page_items<T>(page: Page<T>) -> Vec<T> {
+    if !page.is_last_page {
+        let pages = (page.total_items + page.page_size - 1) / page.page_size.max(1);
+        eprintln!(
+            "Showing page {} of {} ({} items); use --page or --all to see the rest",
+            page.page_index + 1,
+            pages,
+            page.total_items
+        );
+    }
+    page.items
+}
+
+/// Prints each changed setting's old value in red and its new value in green
+pub(crate) fn print_changes(changes: &[(&str, String, String)]) {
+    let width = changes
+        .iter()
+        .map(|(l, _, _)| l.len() + 1)
+        .max()
+        .unwrap_or(0);
+    for (label, before, after) in changes {
+        println!(
+            "  {:<w$} {} -> {}",
+            format!("{}:", label),
+            before.red(),
+            after.green(),
+            w = width
+        );
+    }
+}
+
+/// Prints added variables in green, removed ones in red and changed ones with
+/// both values, masking the values unless `reveal` is set
+pub(crate) fn print_variable_changes(
+    changes: &[(String, Option<String>, Option<String>)],
+    reveal: bool,
+) {
+    let shown = |value: &str| {
+        if reveal {
+            value.to_owned()
+        } else {
+            MASKED_VALUE.to_owned()
+        }
+    };
+    for (key, before, after) in changes {
+        match (before, after) {
+            (None, Some(after)) => println!("{}", format!("+ {}={}", key, shown(after)).green()),
+            (Some(before), None) => println!("{}", format!("- {}={}", key, shown(before)).red()),
+            (Some(before), Some(after)) if before == after => {
+                println!("~ {}: {} (removing duplicates)", key, shown(after).green())
+            }
+            (Some(before), Some(after)) => println!(
+                "~ {}: {} -> {}",
+                key,
+                shown(before).red(),
+                shown(after).green()
+            ),
+            (None, None) => {}
+        }
+    }
+}
+
+/// Prints rows in aligned columns under a bold header, indented by `indent` spaces
+pub(crate) fn print_table(headers: &[&str], rows: &[Vec<String>], indent: usize) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    // pad before colouring, as the colour codes would otherwise count towards the width
+    let header = headers
+        .iter()
+        .zip(&widths)
+        .map(|(h, w)| format!("{:<w$}", h, w = w).bold().to_string())
+        .collect::<Vec<_>>();
+    println!(
+        "{:indent$}{}",
+        "",
+        header.join("   ").trim_end(),
+        indent = indent
+    );
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{:<w$}", c, w = w))
+            .collect::<Vec<_>>();
+        println!(
+            "{:indent$}{}",
+            "",
+            line.join("   ").trim_end(),
+            indent = indent
+        );
+    }
+}
+
+/// When a certificate expires, as listed by `certificate expiring`
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct CertificateExpiry {
+    pub name: String,
+    pub id: String,
+    /// `None` if the certificate could not be parsed
+    pub not_after: Option<DateTime<Utc>>,
+    pub channels: Vec<String>,
+}
+
+/// What is shown in place of environment variable values unless `--reveal` is given
+pub(crate) const MASKED_VALUE: &str = "********";
+
+pub(crate) fn mask_variables(variables: &mut [EnvironmentVariableItem]) {
+    for variable in variables {
+        variable.value = MASKED_VALUE.to_owned();
+    }
+}
+
+/// Masks the channel's environment variables and the private key of its certificate
+pub(crate) fn mask_channel(channel: &mut ChannelItem) {
+    mask_variables(&mut channel.environment_variables);
+    if let Some(certificate) = &mut channel.certificate {
+        certificate.private_key = MASKED_VALUE.to_owned();
+    }
+}
+
+pub(crate) fn mask_channels(channels: &mut [ChannelItem]) {
+    for channel in channels {
+        mask_channel(channel);
+    }
+}
+
+/// Masks the certificate's private key and the environment variables of its channels
+pub(crate) fn mask_certificate(certificate: &mut CertificateItem) {
+    certificate.private_key = MASKED_VALUE.to_owned();
+    mask_channels(&mut certificate.channels);
+}
+
+pub(crate) fn or_none(value: Option<&str>) -> String {
+    match value {
+        Some(v) if !v.is_empty() => v.to_owned(),
+        _ => "<none>".to_owned(),
+    }
+}
+
+impl Tabular for AppItem {
+    fn headers(wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["NAME", "ID", "STORAGE ID", "CHANNELS"];
+        if wide {
+            headers.push("DESCRIPTION");
+        }
+        headers
+    }
+
+    fn row(&self, wide: bool) -> Vec<String> {
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| match &c.active_revision_number {
+                Some(r) if wide => format!("{} ({})", c.name, r),
+                _ => c.name.clone(),
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut row = vec![
+            self.name.clone(),
+            self.id.clone(),
+            self.storage_id.clone(),
+            or_none(Some(&channels)),
+        ];
+        if wide {
+            row.push(or_none(self.description.as_deref()));
+        }
+        row
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Tabular for ChannelItem {
+    fn headers(wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["NAME", "ID", "APP", "DOMAIN", "ACTIVE REVISION"];
+        if wide {
+            headers.extend(["STRATEGY", "RANGE RULE", "CERTIFICATE", "LAST PUBLISHED"]);
+        }
+        headers
+    }
+
+    fn row(&self, wide: bool) -> Vec<String> {
+        let app = match &self.app_summary {
+            Some(a) => a.name.clone(),
+            None => self.app_id.clone(),
+        };
+        let mut row = vec![
+            self.name.clone(),
+            self.id.clone(),
+            app,
+            or_none(Some(&self.domain)),
+            or_none(
+                self.active_revision
+                    .as_ref()
+                    .map(|r| r.revision_number.as_str()),
+            ),
+        ];
+        if wide {
+            row.push(self.revision_selection_strategy.to_string());
+            row.push(or_none(self.range_rule.as_deref()));
+            row.push(or_none(self.certificate.as_ref().map(|c| c.name.as_str())));
+            row.push(or_none(self.last_publish_at.as_deref()));
+        }
+        row
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Tabular for CertificateItem {
+    fn headers(wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["NAME", "ID", "CHANNELS"];
+        if wide {
+            headers.push("DOMAINS");
+        }
+        headers
+    }
+
+    fn row(&self, wide: bool) -> Vec<String> {
+        let channels = self
+            .channels
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut row = vec![self.name.clone(), self.id.clone(), or_none(Some(&channels))];
+        if wide {
+            let domains = self
+                .channels
+                .iter()
+                .map(|c| c.domain.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            row.push(or_none(Some(&domains)));
+        }
+        row
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Tabular for RevisionItem {
+    fn headers(wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["REVISION", "ID", "APP ID"];
+        if wide {
+            headers.extend(["TYPE", "COMPONENTS"]);
+        }
+        headers
+    }
+
+    fn row(&self, wide: bool) -> Vec<String> {
+        let mut row = vec![
+            self.revision_number.clone(),
+            self.id.clone(),
+            self.app_id.clone(),
+        ];
+        if wide {
+            row.push(or_none(self._type.as_deref()));
+            row.push(self.components.len().to_string());
+        }
+        row
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl Tabular for EnvironmentVariableItem {
+    fn headers(wide: bool) -> Vec<&'static str> {
+        let mut headers = vec!["KEY", "VALUE"];
+        if wide {
+            headers.push("CHANNEL ID");
+        }
+        headers
+    }
+
+    fn row(&self, wide: bool) -> Vec<String> {
+        let mut row = vec![self.key.clone(), self.value.clone()];
+        if wide {
+            row.push(self.channel_id.clone());
+        }
+        row
+    }
+
+    // environment variables are identified by their key within a channel
+    fn id(&self) -> &str {
+        &self.key
+    }
+}
+
+impl Tabular for CertificateExpiry {
+    fn headers(_wide: bool) -> Vec<&'static str> {
+        vec!["NAME", "ID", "EXPIRES", "CHANNELS"]
+    }
+
+    fn row(&self, _wide: bool) -> Vec<String> {
+        let expires = match self.not_after {
+            Some(t) => format!("{} ({})", t.format("%Y-%m-%d"), session::describe_expiry(t)),
+            None => "<unknown>".to_owned(),
+        };
+        vec![
+            self.name.clone(),
+            self.id.clone(),
+            expires,
+            or_none(Some(&self.channels.join(", "))),
+        ]
+    }
+
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Duration, Utc};
+use dialoguer::{Confirm, Input, Password};
+use std::io::IsTerminal;
+use std::path::Path;
+
+use super::config::{HippoConfig, Profile, DEFAULT_PROFILE};
+use crate::client::{Client, ConnectionInfo};
+
+/// Sessions closer than this to expiring get a warning on every command
+const EXPIRY_WARNING_MINUTES: i64 = 60;
+
+/// Whether we can prompt the user, rather than running from a script or CI job
+pub(crate) fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Checks the profile's token before it is used. An expired token is renewed
+/// interactively, or reported as an error when there is nobody to prompt.
+pub(crate) async fn ensure_session(
+    hippo_conf: &mut HippoConfig,
+    hippo_config_path: &Path,
+    profile_name: &str,
+    profile: Profile,
+) -> anyhow::Result<Profile> {
+    let expiry = match profile.token_expiry() {
+        Some(e) => e,
+        None => return Ok(profile),
+    };
+    let now = Utc::now();
+
+    if expiry <= now {
+        if !is_interactive() {
+            return Err(session_expired(profile_name));
+        }
+        eprintln!(
+            "Your session for profile '{}' expired {}.",
+            profile_name,
+            describe_expiry(expiry)
+        );
+        if !Confirm::new()
+            .with_prompt("Log in again?")
+            .default(true)
+            .interact()?
+        {
+            return Err(session_expired(profile_name));
+        }
+        return renew(hippo_conf, hippo_config_path, profile_name, profile).await;
+    }
+
+    if expiry - now < Duration::minutes(EXPIRY_WARNING_MINUTES) {
+        eprintln!(
+            "warning: your session for profile '{}' expires {}; run '{}' to renew it",
+            profile_name,
+            describe_expiry(expiry),
+            login_command(profile_name)
+        );
+    }
+    Ok(profile)
+}
+
+/// Prompts for the profile's password, logs in again and saves the new token
+pub(crate) async fn renew(
+    hippo_conf: &mut HippoConfig,
+    hippo_config_path: &Path,
+    profile_name: &str,
+    mut profile: Profile,
+) -> anyhow::Result<Profile> {
+    if profile.username.is_empty() {
+        profile.username = Input::new().with_prompt("Enter username").interact_text()?;
+    }
+    let password: String = Password::new()
+        .with_prompt(format!("Enter password for {}", profile.username))
+        .interact()?;
+    let client = Client::new(ConnectionInfo {
+        url: profile.url.clone(),
+        danger_accept_invalid_certs: profile.danger_accept_invalid_certs,
+        api_key: None,
+    });
+    let token_info = client.login(profile.username.clone(), password).await?;
+    profile.store_token(profile_name, hippo_config_path, token_info)?;
+    hippo_conf
+        .profiles
+        .insert(profile_name.to_owned(), profile.clone());
+    hippo_conf.save(hippo_config_path)?;
+    eprintln!("Logged in as {}", profile.username);
+    Ok(profile)
+}
+
+pub(crate) fn session_expired(profile_name: &str) -> anyhow::Error {
+    anyhow::anyhow!(
+        "session expired, run '{}' to log in again",
+        login_command(profile_name)
+    )
+}
+
+/// Describes an expiry time relative to now, e.g. "in 3 hours" or "2 days ago"
+pub(crate) fn describe_expiry(expiry: DateTime<Utc>) -> String {
+    let delta = expiry - Utc::now();
+    let (amount, unit) = {
+        let d = if delta < Duration::zero() {
+            -delta
+        } else {
+            delta
+        };
+        if d.num_days() > 0 {
+            (d.num_days(), "day")
+        } else if d.num_hours() > 0 {
+            (d.num_hours(), "hour")
+        } else {
+            (d.num_minutes(), "minute")
+        }
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if delta < Duration::zero() {
+        format!("{} {}{} ago", amount, unit, plural)
+    } else {
+        format!("in {} {}{}", amount, unit, plural)
+    }
+}
+
+fn login_command(profile_name: &str) -> String {
+    if profile_name == DEFAULT_PROFILE {
+        "hippo login".to_owned()
+    } else {
+        format!("hippo login --profile {}", profile_name)
+    }
+}
@@ -3,27 +3,31 @@ use hippo_openapi::models::PatchChannelCommand;
 use std::collections::HashMap;
 
 use hippo_openapi::apis::account_api::{api_account_createtoken_post, api_account_post};
-use hippo_openapi::apis::app_api::{api_app_get, api_app_id_delete, api_app_post};
+use hippo_openapi::apis::app_api::{api_app_get, api_app_id_delete, api_app_id_put, api_app_post};
 use hippo_openapi::apis::certificate_api::{
-    api_certificate_get, api_certificate_id_delete, api_certificate_post,
+    api_certificate_get, api_certificate_id_delete, api_certificate_id_put, api_certificate_post,
 };
 use hippo_openapi::apis::channel_api::{
     api_channel_get, api_channel_id_delete, api_channel_id_get, api_channel_id_patch,
-    api_channel_post, api_channel_logs_id_get
+    api_channel_logs_id_get, api_channel_post,
 };
 use hippo_openapi::apis::configuration::{ApiKey, Configuration};
+use hippo_openapi::apis::job_status_api::api_jobstatus_channel_id_get;
 use hippo_openapi::apis::revision_api::{api_revision_get, api_revision_post};
 use hippo_openapi::apis::Error;
 use hippo_openapi::models::{
-    AppItemPage, CertificateItemPage, ChannelItem, ChannelItemPage,
-    ChannelRevisionSelectionStrategy, CreateAccountCommand, CreateAppCommand,
+    AppItem, AppItemPage, CertificateItem, CertificateItemPage, ChannelItem, ChannelItemPage,
+    ChannelJobStatusItem, ChannelRevisionSelectionStrategy, CreateAccountCommand, CreateAppCommand,
     CreateCertificateCommand, CreateChannelCommand, CreateTokenCommand, EnvironmentVariableItem,
-    RegisterRevisionCommand, RevisionItemPage, TokenInfo, UpdateEnvironmentVariableDto,
-    UpdateEnvironmentVariableDtoListField,
+    RegisterRevisionCommand, RevisionItem, RevisionItemPage, TokenInfo, UpdateAppCommand,
+    UpdateCertificateCommand, UpdateEnvironmentVariableDto, UpdateEnvironmentVariableDtoListField,
 };
 
-use reqwest::header;
+use futures::{future, stream, Stream, TryStreamExt};
+use reqwest::{header, StatusCode};
 use serde::Deserialize;
+use std::fmt;
+use std::future::Future;
 
 const JSON_MIME_TYPE: &str = "application/json";
 
@@ -45,10 +49,10 @@ impl Client {
 
         let base_path = match conn_info.url.strip_suffix("/") {
             Some(s) => s.to_owned(),
-            None => conn_info.url
+            None => conn_info.url,
         };
         let configuration = Configuration {
-            base_path: base_path,
+            base_path,
             user_agent: Some(format!(
                 "{}/{}",
                 env!("CARGO_PKG_NAME"),
@@ -62,11 +66,9 @@ impl Client {
             basic_auth: None,
             oauth_access_token: None,
             bearer_access_token: None,
-            api_key: conn_info.api_key.map_or(None, |t| {
-                Some(ApiKey {
-                    prefix: Some("Bearer".to_owned()),
-                    key: t,
-                })
+            api_key: conn_info.api_key.map(|t| ApiKey {
+                prefix: Some("Bearer".to_owned()),
+                key: t,
             }),
         };
 
@@ -78,7 +80,7 @@ impl Client {
             &self.configuration,
             Some(CreateAccountCommand {
                 user_name: username,
-                password: password,
+                password,
             }),
         )
         .await
@@ -90,7 +92,7 @@ impl Client {
             &self.configuration,
             Some(CreateTokenCommand {
                 user_name: username,
-                password: password,
+                password,
             }),
         )
         .await
@@ -100,9 +102,19 @@ impl Client {
     pub async fn add_app(&self, name: String, storage_id: String) -> anyhow::Result<String> {
         api_app_post(
             &self.configuration,
-            Some(CreateAppCommand {
-                name: name,
-                storage_id: storage_id,
+            Some(CreateAppCommand { name, storage_id }),
+        )
+        .await
+        .map_err(format_response_error)
+    }
+
+    pub async fn update_app(&self, id: String, name: String) -> anyhow::Result<()> {
+        api_app_id_put(
+            &self.configuration,
+            &id,
+            Some(UpdateAppCommand {
+                id: id.clone(),
+                name,
             }),
         )
         .await
@@ -115,12 +127,48 @@ impl Client {
             .map_err(format_response_error)
     }
 
+    /// Fetches the first page of apps, as sized by the server
+    #[allow(dead_code)]
     pub async fn list_apps(&self) -> anyhow::Result<AppItemPage> {
         api_app_get(&self.configuration, None, None, None, None, None)
             .await
             .map_err(format_response_error)
     }
 
+    /// Lists every app, fetching as many pages as it takes
+    pub async fn list_all_apps(&self) -> anyhow::Result<Vec<AppItem>> {
+        self.apps(Default::default()).try_collect().await
+    }
+
+    pub async fn list_apps_page(&self, options: &ListOptions) -> anyhow::Result<Page<AppItem>> {
+        api_app_get(
+            &self.configuration,
+            options.search_text.as_deref(),
+            options.page_index,
+            options.page_size,
+            options.sort_by.as_deref(),
+            options.sort_ascending,
+        )
+        .await
+        .map(Page::from)
+        .map_err(format_response_error)
+    }
+
+    /// Fetches one app. The API has no endpoint for this, so it searches the app list.
+    pub async fn get_app(&self, id: &str) -> anyhow::Result<AppItem> {
+        find_first(self.apps(Default::default()), |a| a.id == id)
+            .await?
+            .ok_or_else(|| NotFound::error(format!("no app with ID '{}'", id)))
+    }
+
+    /// Yields apps one at a time, fetching pages as they are needed
+    pub fn apps(&self, options: ListOptions) -> impl Stream<Item = anyhow::Result<AppItem>> + '_ {
+        paginate(
+            options,
+            move |o| async move { self.list_apps_page(&o).await },
+        )
+    }
+
     pub async fn add_certificate(
         &self,
         name: String,
@@ -130,27 +178,91 @@ impl Client {
         api_certificate_post(
             &self.configuration,
             Some(CreateCertificateCommand {
-                name: name,
-                public_key: public_key,
-                private_key: private_key,
+                name,
+                public_key,
+                private_key,
             }),
         )
         .await
         .map_err(format_response_error)
     }
 
+    /// Fetches the first page of certificates, as sized by the server
+    #[allow(dead_code)]
     pub async fn list_certificates(&self) -> anyhow::Result<CertificateItemPage> {
         api_certificate_get(&self.configuration, None, None, None, None, None)
             .await
             .map_err(format_response_error)
     }
 
+    /// Lists every certificate, fetching as many pages as it takes
+    pub async fn list_all_certificates(&self) -> anyhow::Result<Vec<CertificateItem>> {
+        self.certificates(Default::default()).try_collect().await
+    }
+
+    pub async fn list_certificates_page(
+        &self,
+        options: &ListOptions,
+    ) -> anyhow::Result<Page<CertificateItem>> {
+        api_certificate_get(
+            &self.configuration,
+            options.search_text.as_deref(),
+            options.page_index,
+            options.page_size,
+            options.sort_by.as_deref(),
+            options.sort_ascending,
+        )
+        .await
+        .map(Page::from)
+        .map_err(format_response_error)
+    }
+
+    /// Fetches one certificate. The API has no endpoint for this, so it
+    /// searches the certificate list.
+    pub async fn get_certificate(&self, id: &str) -> anyhow::Result<CertificateItem> {
+        find_first(self.certificates(Default::default()), |c| c.id == id)
+            .await?
+            .ok_or_else(|| NotFound::error(format!("no certificate with ID '{}'", id)))
+    }
+
+    /// Yields certificates one at a time, fetching pages as they are needed
+    pub fn certificates(
+        &self,
+        options: ListOptions,
+    ) -> impl Stream<Item = anyhow::Result<CertificateItem>> + '_ {
+        paginate(options, move |o| async move {
+            self.list_certificates_page(&o).await
+        })
+    }
+
+    pub async fn update_certificate(
+        &self,
+        id: String,
+        name: String,
+        public_key: String,
+        private_key: String,
+    ) -> anyhow::Result<()> {
+        api_certificate_id_put(
+            &self.configuration,
+            &id,
+            Some(UpdateCertificateCommand {
+                id: id.clone(),
+                name,
+                public_key,
+                private_key,
+            }),
+        )
+        .await
+        .map_err(format_response_error)
+    }
+
     pub async fn remove_certificate(&self, id: String) -> anyhow::Result<()> {
         api_certificate_id_delete(&self.configuration, &id)
             .await
             .map_err(format_response_error)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn add_channel(
         &self,
         app_id: String,
@@ -162,8 +274,8 @@ impl Client {
         certificate_id: Option<String>,
     ) -> anyhow::Result<String> {
         let command = CreateChannelCommand {
-            app_id: app_id,
-            name: name,
+            app_id,
+            name,
             domain,
             revision_selection_strategy,
             range_rule,
@@ -181,12 +293,63 @@ impl Client {
             .map_err(format_response_error)
     }
 
+    pub async fn get_channel_status(&self, id: &str) -> anyhow::Result<ChannelJobStatusItem> {
+        api_jobstatus_channel_id_get(&self.configuration, id)
+            .await
+            .map_err(format_response_error)
+    }
+
+    /// Fetches the first page of channels, as sized by the server
+    #[allow(dead_code)]
     pub async fn list_channels(&self) -> anyhow::Result<ChannelItemPage> {
         api_channel_get(&self.configuration, None, None, None, None, None)
             .await
             .map_err(format_response_error)
     }
 
+    /// Lists every channel, fetching as many pages as it takes
+    pub async fn list_all_channels(&self) -> anyhow::Result<Vec<ChannelItem>> {
+        self.channels(Default::default()).try_collect().await
+    }
+
+    pub async fn list_channels_page(
+        &self,
+        options: &ListOptions,
+    ) -> anyhow::Result<Page<ChannelItem>> {
+        api_channel_get(
+            &self.configuration,
+            options.search_text.as_deref(),
+            options.page_index,
+            options.page_size,
+            options.sort_by.as_deref(),
+            options.sort_ascending,
+        )
+        .await
+        .map(Page::from)
+        .map_err(format_response_error)
+    }
+
+    /// Yields channels one at a time, fetching pages as they are needed
+    pub fn channels(
+        &self,
+        options: ListOptions,
+    ) -> impl Stream<Item = anyhow::Result<ChannelItem>> + '_ {
+        paginate(options, move |o| async move {
+            self.list_channels_page(&o).await
+        })
+    }
+
+    /// Changes the fields of a channel that are set in the command
+    pub async fn patch_channel(
+        &self,
+        id: &str,
+        command: PatchChannelCommand,
+    ) -> anyhow::Result<()> {
+        api_channel_id_patch(&self.configuration, id, Some(command))
+            .await
+            .map_err(format_response_error)
+    }
+
     pub async fn remove_channel(&self, id: String) -> anyhow::Result<()> {
         api_channel_id_delete(&self.configuration, &id)
             .await
@@ -206,33 +369,32 @@ impl Client {
         channel_id: String,
     ) -> anyhow::Result<()> {
         let mut environment_variables = self.list_environment_variables(channel_id.clone()).await?;
+        if environment_variables.iter().any(|e| e.key == key) {
+            anyhow::bail!("environment variable {} is already set on the channel", key);
+        }
         environment_variables.push(EnvironmentVariableItem {
             // TODO: fix this in hippo 0.19 - shouldn't need to reference the channel ID
             channel_id: channel_id.clone(),
-            key: key,
-            value: value,
+            key,
+            value,
         });
-        api_channel_id_patch(
-            &self.configuration,
-            &channel_id,
-            Some(PatchChannelCommand {
-                // TODO: fix this in hippo 0.19 - this is a very ugly type cast that shouldn't exist
-                environment_variables: Some(Box::new(UpdateEnvironmentVariableDtoListField {
-                    value: Some(
-                        environment_variables
-                            .iter()
-                            .map(|e| UpdateEnvironmentVariableDto {
-                                key: e.key.clone(),
-                                value: e.value.clone(),
-                            })
-                            .collect(),
-                    ),
-                })),
-                ..Default::default()
-            }),
-        )
-        .await
-        .map_err(format_response_error)
+        self.set_environment_variables(&channel_id, to_pairs(environment_variables))
+            .await
+    }
+
+    /// Sets an environment variable, replacing its value if the channel already
+    /// has it. Returns whether it replaced an existing value.
+    pub async fn set_environment_variable(
+        &self,
+        key: String,
+        value: String,
+        channel_id: String,
+    ) -> anyhow::Result<bool> {
+        let mut variables = to_pairs(self.list_environment_variables(channel_id.clone()).await?);
+        let replaced = upsert_variable(&mut variables, key, value);
+        self.set_environment_variables(&channel_id, variables)
+            .await?;
+        Ok(replaced)
     }
 
     pub async fn list_environment_variables(
@@ -249,24 +411,31 @@ impl Client {
         key: String,
     ) -> anyhow::Result<()> {
         let mut environment_variables = self.list_environment_variables(channel_id.clone()).await?;
-        let index = environment_variables
-            .iter()
-            .position(|e| e.key == key)
-            .unwrap();
-        environment_variables.remove(index);
+        if !environment_variables.iter().any(|e| e.key == key) {
+            return Err(anyhow::Error::new(VariableNotSet { key }));
+        }
+        environment_variables.retain(|e| e.key != key);
+        self.set_environment_variables(&channel_id, to_pairs(environment_variables))
+            .await
+    }
+
+    /// Replaces all of a channel's environment variables with a single patch,
+    /// so the channel is only redeployed once
+    pub async fn set_environment_variables(
+        &self,
+        channel_id: &str,
+        variables: Vec<(String, String)>,
+    ) -> anyhow::Result<()> {
         api_channel_id_patch(
             &self.configuration,
-            &channel_id,
+            channel_id,
             Some(PatchChannelCommand {
                 // TODO: fix this in hippo 0.19 - this is a very ugly type cast that shouldn't exist
                 environment_variables: Some(Box::new(UpdateEnvironmentVariableDtoListField {
                     value: Some(
-                        environment_variables
-                            .iter()
-                            .map(|e| UpdateEnvironmentVariableDto {
-                                key: e.key.clone(),
-                                value: e.value.clone(),
-                            })
+                        variables
+                            .into_iter()
+                            .map(|(key, value)| UpdateEnvironmentVariableDto { key, value })
                             .collect(),
                     ),
                 })),
@@ -285,19 +454,254 @@ impl Client {
         api_revision_post(
             &self.configuration,
             Some(RegisterRevisionCommand {
-                app_storage_id: app_storage_id,
-                revision_number: revision_number,
+                app_storage_id,
+                revision_number,
             }),
         )
         .await
         .map_err(format_response_error)
     }
 
+    /// Fetches the first page of revisions, as sized by the server
+    #[allow(dead_code)]
     pub async fn list_revisions(&self) -> anyhow::Result<RevisionItemPage> {
         api_revision_get(&self.configuration, None, None)
             .await
             .map_err(format_response_error)
     }
+
+    /// Lists every revision, fetching as many pages as it takes
+    pub async fn list_all_revisions(&self) -> anyhow::Result<Vec<RevisionItem>> {
+        self.revisions(Default::default()).try_collect().await
+    }
+
+    pub async fn list_revisions_page(
+        &self,
+        options: &ListOptions,
+    ) -> anyhow::Result<Page<RevisionItem>> {
+        api_revision_get(&self.configuration, options.page_index, options.page_size)
+            .await
+            .map(Page::from)
+            .map_err(format_response_error)
+    }
+
+    /// Fetches one revision. The API has no endpoint for this, so it searches
+    /// the revision list.
+    pub async fn get_revision(&self, id: &str) -> anyhow::Result<RevisionItem> {
+        find_first(self.revisions(Default::default()), |r| r.id == id)
+            .await?
+            .ok_or_else(|| NotFound::error(format!("no revision with ID '{}'", id)))
+    }
+
+    /// Yields revisions one at a time, fetching pages as they are needed
+    pub fn revisions(
+        &self,
+        options: ListOptions,
+    ) -> impl Stream<Item = anyhow::Result<RevisionItem>> + '_ {
+        paginate(options, move |o| async move {
+            self.list_revisions_page(&o).await
+        })
+    }
+
+    /// Finds the ID of an app from its name or ID
+    pub async fn resolve_app(&self, reference: &str) -> anyhow::Result<String> {
+        if is_id(reference) {
+            return Ok(reference.to_owned());
+        }
+        let matches = self
+            .list_all_apps()
+            .await?
+            .into_iter()
+            .filter(|a| a.name == reference)
+            .map(|a| (a.id, a.name))
+            .collect();
+        single_match("app", reference, matches)
+    }
+
+    /// Finds the ID of a channel from its ID, its name, or its name qualified
+    /// by the app's name or ID, such as `helloworld/latest`
+    pub async fn resolve_channel(&self, reference: &str) -> anyhow::Result<String> {
+        if is_id(reference) {
+            return Ok(reference.to_owned());
+        }
+        let (app, name) = match reference.split_once('/') {
+            Some((app, name)) => (Some(app), name),
+            None => (None, reference),
+        };
+        let mut matches = vec![];
+        for c in self.list_all_channels().await? {
+            let app_name = match &c.app_summary {
+                Some(a) => a.name.clone(),
+                None => c.app_id.clone(),
+            };
+            if c.name == name && app.map_or(true, |a| a == c.app_id || a == app_name) {
+                matches.push((c.id, format!("{}/{}", app_name, c.name)));
+            }
+        }
+        single_match("channel", reference, matches)
+    }
+
+    /// Finds the ID of a certificate from its name or ID
+    pub async fn resolve_certificate(&self, reference: &str) -> anyhow::Result<String> {
+        if is_id(reference) {
+            return Ok(reference.to_owned());
+        }
+        let matches = self
+            .list_all_certificates()
+            .await?
+            .into_iter()
+            .filter(|c| c.name == reference)
+            .map(|c| (c.id, c.name))
+            .collect();
+        single_match("certificate", reference, matches)
+    }
+
+    /// Finds the ID of one of an app's revisions from its revision number or ID
+    pub async fn resolve_revision(&self, app_id: &str, reference: &str) -> anyhow::Result<String> {
+        if is_id(reference) {
+            return Ok(reference.to_owned());
+        }
+        let matches = self
+            .list_all_revisions()
+            .await?
+            .into_iter()
+            .filter(|r| r.app_id == app_id && r.revision_number == reference)
+            .map(|r| (r.id, r.revision_number))
+            .collect();
+        single_match("revision", reference, matches)
+    }
+}
+
+/// Which items a list API returns
+#[derive(Clone, Default)]
+pub struct ListOptions {
+    /// The zero-based index of the page to return, or the first page if not set
+    pub page_index: Option<i32>,
+    /// The number of items per page, or the server's default if not set
+    pub page_size: Option<i32>,
+    /// Only return items matching this text. Revisions cannot be searched.
+    pub search_text: Option<String>,
+    /// The field to sort by. Revisions cannot be sorted.
+    pub sort_by: Option<String>,
+    /// Whether to sort in ascending order, or the server's default if not set
+    pub sort_ascending: Option<bool>,
+}
+
+/// One page of the items returned by a list API
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total_items: i32,
+    pub page_index: i32,
+    pub page_size: i32,
+    pub is_last_page: bool,
+}
+
+macro_rules! impl_from_item_page {
+    ($page:ty, $item:ty) => {
+        impl From<$page> for Page<$item> {
+            fn from(page: $page) -> Self {
+                Self {
+                    items: page.items,
+                    total_items: page.total_items,
+                    page_index: page.page_index,
+                    page_size: page.page_size,
+                    is_last_page: page.is_last_page,
+                }
+            }
+        }
+    };
+}
+
+impl_from_item_page!(AppItemPage, AppItem);
+impl_from_item_page!(CertificateItemPage, CertificateItem);
+impl_from_item_page!(ChannelItemPage, ChannelItem);
+impl_from_item_page!(RevisionItemPage, RevisionItem);
+
+/// The page size used when walking every page, unless the caller chose one
+const STREAM_PAGE_SIZE: i32 = 100;
+
+/// Turns a function that fetches one page into a stream of the items on that
+/// page and every page after it
+fn paginate<'a, T, F, Fut>(
+    options: ListOptions,
+    fetch_page: F,
+) -> impl Stream<Item = anyhow::Result<T>> + 'a
+where
+    T: 'a,
+    F: Fn(ListOptions) -> Fut + 'a,
+    Fut: Future<Output = anyhow::Result<Page<T>>> + 'a,
+{
+    let options = ListOptions {
+        page_index: Some(options.page_index.unwrap_or(0)),
+        page_size: Some(options.page_size.unwrap_or(STREAM_PAGE_SIZE)),
+        ..options
+    };
+    stream::try_unfold(
+        (Some(options), fetch_page),
+        |(options, fetch_page)| async move {
+            let options = match options {
+                Some(o) => o,
+                None => return anyhow::Ok(None),
+            };
+            let page = fetch_page(options.clone()).await?;
+            let next = if page.is_last_page || page.items.is_empty() {
+                None
+            } else {
+                Some(ListOptions {
+                    page_index: options.page_index.map(|i| i + 1),
+                    ..options
+                })
+            };
+            let items = stream::iter(page.items.into_iter().map(Ok));
+            Ok(Some((items, (next, fetch_page))))
+        },
+    )
+    .try_flatten()
+}
+
+/// Finds the first item in a stream that matches, without fetching any more pages
+async fn find_first<T>(
+    items: impl Stream<Item = anyhow::Result<T>>,
+    matches: impl Fn(&T) -> bool,
+) -> anyhow::Result<Option<T>> {
+    let found = items.try_filter(|i| future::ready(matches(i)));
+    futures::pin_mut!(found);
+    found.try_next().await
+}
+
+/// Whether a command line argument is a Hippo ID (a GUID) rather than a name
+fn is_id(reference: &str) -> bool {
+    let groups: Vec<&str> = reference.split('-').collect();
+    groups.iter().map(|g| g.len()).eq([8, 4, 4, 4, 12])
+        && groups
+            .iter()
+            .all(|g| g.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// Picks the ID of the only resource a name refers to, given the `(id,
+/// description)` of each resource with that name
+fn single_match(
+    kind: &str,
+    reference: &str,
+    matches: Vec<(String, String)>,
+) -> anyhow::Result<String> {
+    match matches.len() {
+        0 => anyhow::bail!("no {} named '{}'", kind, reference),
+        1 => Ok(matches.into_iter().next().unwrap().0),
+        _ => {
+            let candidates = matches
+                .iter()
+                .map(|(id, description)| format!("{} ({})", description, id))
+                .collect::<Vec<_>>()
+                .join(", ");
+            anyhow::bail!(
+                "{} name '{}' is ambiguous; it could be {}",
+                kind,
+                reference,
+                candidates
+            )
+        }
+    }
 }
 
 #[derive(Deserialize, Debug)]
@@ -306,14 +710,151 @@ struct ValidationExceptionMessage {
     errors: HashMap<String, Vec<String>>,
 }
 
+/// Returned when Hippo rejects the request's token, usually because the
+/// session has expired
+#[derive(Debug)]
+pub struct Unauthorized;
+
+impl fmt::Display for Unauthorized {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "the Hippo server rejected the login token")
+    }
+}
+
+impl std::error::Error for Unauthorized {}
+
+/// Returned when Hippo has no such resource, or is too old to support the
+/// request at all
+#[derive(Debug)]
+pub struct NotFound {
+    pub message: String,
+}
+
+impl NotFound {
+    fn error(message: String) -> anyhow::Error {
+        anyhow::Error::new(NotFound { message })
+    }
+}
+
+impl fmt::Display for NotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for NotFound {}
+
+/// Returned when removing an environment variable that the channel does not have
+#[derive(Debug)]
+pub struct VariableNotSet {
+    pub key: String,
+}
+
+impl fmt::Display for VariableNotSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "environment variable {} is not set on the channel",
+            self.key
+        )
+    }
+}
+
+impl std::error::Error for VariableNotSet {}
+
+/// Sets `key` in a list of variables, in place of its first occurrence or at
+/// the end, and removes any other occurrences. Returns whether it was already set.
+pub(crate) fn upsert_variable(
+    variables: &mut Vec<(String, String)>,
+    key: String,
+    value: String,
+) -> bool {
+    let existing = variables.iter().position(|(k, _)| *k == key);
+    variables.retain(|(k, _)| *k != key);
+    match existing {
+        Some(index) => variables.insert(index, (key, value)),
+        None => variables.push((key, value)),
+    }
+    existing.is_some()
+}
+
+fn to_pairs(environment_variables: Vec<EnvironmentVariableItem>) -> Vec<(String, String)> {
+    environment_variables
+        .into_iter()
+        .map(|e| (e.key, e.value))
+        .collect()
+}
+
 fn format_response_error<T>(e: Error<T>) -> anyhow::Error {
     match e {
+        Error::ResponseError(r) if r.status == StatusCode::UNAUTHORIZED => {
+            anyhow::Error::new(Unauthorized)
+        }
         Error::ResponseError(r) => {
-            match serde_json::from_str::<ValidationExceptionMessage>(&r.content) {
-                Ok(m) => anyhow::anyhow!("{} {:?}", m.title, m.errors),
-                _ => anyhow::anyhow!(r.content),
+            let message = match serde_json::from_str::<ValidationExceptionMessage>(&r.content) {
+                Ok(m) => format!("{} {:?}", m.title, m.errors),
+                _ => r.content,
+            };
+            if r.status == StatusCode::NOT_FOUND {
+                NotFound::error(message)
+            } else {
+                anyhow::anyhow!(message)
             }
         }
         _ => anyhow::anyhow!(e.to_string()),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upsert_replaces_a_variable_in_place() {
+        let mut variables = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+            ("C".to_string(), "3".to_string()),
+        ];
+        assert!(upsert_variable(&mut variables, "B".into(), "20".into()));
+        assert_eq!(
+            variables,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "20".to_string()),
+                ("C".to_string(), "3".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn upsert_appends_a_new_variable() {
+        let mut variables = vec![("A".to_string(), "1".to_string())];
+        assert!(!upsert_variable(&mut variables, "B".into(), "2".into()));
+        assert_eq!(
+            variables,
+            vec![
+                ("A".to_string(), "1".to_string()),
+                ("B".to_string(), "2".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn upsert_collapses_duplicates_into_the_first() {
+        let mut variables = vec![
+            ("A".to_string(), "1".to_string()),
+            ("B".to_string(), "2".to_string()),
+            ("A".to_string(), "3".to_string()),
+            ("A".to_string(), "4".to_string()),
+        ];
+        assert!(upsert_variable(&mut variables, "A".into(), "5".into()));
+        assert_eq!(
+            variables,
+            vec![
+                ("A".to_string(), "5".to_string()),
+                ("B".to_string(), "2".to_string())
+            ]
+        );
+    }
+}
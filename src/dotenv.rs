@@ -0,0 +1,221 @@
+use std::collections::HashMap;
+
+/// Parses the `KEY=value` lines of a dotenv file, in the order they appear.
+///
+/// Blank lines and lines starting with `#` are skipped, as is an `export `
+/// before the key. Unquoted values end at the first ` #`. Single-quoted values
+/// are taken literally; double-quoted values may span lines and understand the
+/// escapes `\n`, `\t`, `\"`, `\\` and `\$`.
+pub fn parse(text: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut variables: Vec<(String, String)> = vec![];
+    let mut first_seen = HashMap::new();
+    let mut lines = text.lines().enumerate();
+
+    while let Some((index, line)) = lines.next() {
+        let line_number = index + 1;
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, rest) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("line {}: expected KEY=value", line_number))?;
+        let key = key.trim();
+        if !is_valid_key(key) {
+            anyhow::bail!(
+                "line {}: '{}' is not a valid variable name",
+                line_number,
+                key
+            );
+        }
+
+        let rest = rest.trim_start();
+        let value = if let Some(quoted) = rest.strip_prefix('\'') {
+            let (value, _) = quoted.split_once('\'').ok_or_else(|| {
+                anyhow::anyhow!("line {}: unterminated single-quoted value", line_number)
+            })?;
+            value.to_owned()
+        } else if let Some(quoted) = rest.strip_prefix('"') {
+            let mut raw = quoted.to_owned();
+            while !has_closing_quote(&raw) {
+                let (_, next) = lines.next().ok_or_else(|| {
+                    anyhow::anyhow!("line {}: unterminated double-quoted value", line_number)
+                })?;
+                raw.push('\n');
+                raw.push_str(next);
+            }
+            unescape(&raw)
+        } else {
+            let value = match rest.find(" #") {
+                Some(comment) => &rest[..comment],
+                None => rest,
+            };
+            value.trim_end().to_owned()
+        };
+
+        if let Some(previous) = first_seen.insert(key.to_owned(), line_number) {
+            anyhow::bail!(
+                "line {}: {} is already set on line {}",
+                line_number,
+                key,
+                previous
+            );
+        }
+        variables.push((key.to_owned(), value));
+    }
+
+    Ok(variables)
+}
+
+/// Writes variables as dotenv lines that `parse` reads back unchanged, quoting
+/// only the values that need it
+pub fn format(variables: &[(String, String)]) -> String {
+    variables
+        .iter()
+        .map(|(key, value)| format!("{}={}\n", key, quote(value)))
+        .collect()
+}
+
+fn is_valid_key(key: &str) -> bool {
+    let mut chars = key.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '.')
+}
+
+/// Whether a double-quoted value read so far contains its unescaped closing quote
+fn has_closing_quote(raw: &str) -> bool {
+    let mut escaped = false;
+    for c in raw.chars() {
+        match c {
+            '\\' if !escaped => escaped = true,
+            '"' if !escaped => return true,
+            _ => escaped = false,
+        }
+    }
+    false
+}
+
+/// The contents of a double-quoted value up to its closing quote, with
+/// escapes replaced
+fn unescape(raw: &str) -> String {
+    let mut value = String::new();
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('t') => value.push('\t'),
+                Some(other @ ('"' | '\\' | '$')) => value.push(other),
+                Some(other) => {
+                    value.push('\\');
+                    value.push(other);
+                }
+                None => value.push('\\'),
+            },
+            _ => value.push(c),
+        }
+    }
+    value
+}
+
+fn quote(value: &str) -> String {
+    let is_plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+%=".contains(c));
+    if is_plain {
+        return value.to_owned();
+    }
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '"' | '\\' | '$' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_plain_quoted_and_commented_lines() {
+        let text = r#"
+# a comment
+export GREETING=hello world # trailing comment
+EMPTY=
+URL=postgres://user@host/db?a=b
+HASH=a#b
+SINGLE='no $escapes\n here'
+DOUBLE="tab\there \"quoted\""
+MULTI="first
+second"
+"#;
+        assert_eq!(
+            parse(text).unwrap(),
+            vec![
+                ("GREETING".to_string(), "hello world".to_string()),
+                ("EMPTY".to_string(), "".to_string()),
+                ("URL".to_string(), "postgres://user@host/db?a=b".to_string()),
+                ("HASH".to_string(), "a#b".to_string()),
+                ("SINGLE".to_string(), "no $escapes\\n here".to_string()),
+                ("DOUBLE".to_string(), "tab\there \"quoted\"".to_string()),
+                ("MULTI".to_string(), "first\nsecond".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_lines() {
+        for (text, error) in [
+            ("JUST_A_KEY", "line 1: expected KEY=value"),
+            ("\n1KEY=x", "line 2: '1KEY' is not a valid variable name"),
+            ("A='open", "line 1: unterminated single-quoted value"),
+            (
+                "A=\"open\nstill open",
+                "line 1: unterminated double-quoted value",
+            ),
+            ("A=1\nA=2", "line 2: A is already set on line 1"),
+        ] {
+            assert_eq!(parse(text).unwrap_err().to_string(), error);
+        }
+    }
+
+    #[test]
+    fn quotes_only_values_that_need_it() {
+        let variables = vec![
+            ("PLAIN".to_string(), "a-b.c:d/e@f".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+            ("SPACE".to_string(), "a b".to_string()),
+        ];
+        assert_eq!(
+            format(&variables),
+            "PLAIN=a-b.c:d/e@f\nEMPTY=\"\"\nSPACE=\"a b\"\n"
+        );
+    }
+
+    #[test]
+    fn format_round_trips_through_parse() {
+        let variables = vec![
+            ("QUOTES".to_string(), "say \"hi\" and 'bye'".to_string()),
+            ("HASH".to_string(), "before # after".to_string()),
+            ("HASH_START".to_string(), "#start".to_string()),
+            ("EQUALS".to_string(), "a=b=c".to_string()),
+            ("NEWLINES".to_string(), "line one\nline two\n".to_string()),
+            ("ESCAPES".to_string(), "back\\slash $HOME\ttab".to_string()),
+            ("PADDED".to_string(), "  padded  ".to_string()),
+            ("EMPTY".to_string(), "".to_string()),
+        ];
+        assert_eq!(parse(&format(&variables)).unwrap(), variables);
+    }
+}
@@ -0,0 +1,434 @@
+use semver::Version;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::path::Path;
+use toml::Spanned;
+
+pub const HIPPOFACTS_FILE_NAME: &str = "HIPPOFACTS";
+
+#[derive(Debug, Clone)]
+pub struct HippoFacts {
+    pub bindle: BindleSpec,
+    pub annotations: Option<BTreeMap<String, String>>,
+    pub handlers: Vec<Handler>,
+}
+
+#[derive(Debug, Clone)]
+pub struct BindleSpec {
+    pub name: String,
+    pub version: Version,
+    pub description: Option<String>,
+    pub authors: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Handler {
+    pub route: String,
+    pub module: HandlerModule,
+    /// Glob patterns, relative to the HIPPOFACTS file, of the files the handler needs at runtime
+    pub files: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum HandlerModule {
+    /// A WebAssembly module on disk, relative to the HIPPOFACTS file
+    File(String),
+    /// A module published as a parcel of another bindle
+    External(ExternalRef),
+}
+
+#[derive(Debug, Clone)]
+pub struct ExternalRef {
+    pub bindle_id: String,
+    pub handler_id: String,
+}
+
+/// A problem found in a HIPPOFACTS file, located by key and (where known) line
+#[derive(Debug, Clone)]
+pub struct FactsError {
+    pub key: String,
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for FactsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(line) = self.line {
+            write!(f, "line {}: ", line)?;
+        }
+        if !self.key.is_empty() {
+            write!(f, "{}: ", self.key)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for FactsError {}
+
+impl HippoFacts {
+    pub fn read_from(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|errors| {
+            let details = errors
+                .iter()
+                .map(|e| format!("  {}", e))
+                .collect::<Vec<_>>()
+                .join("\n");
+            anyhow::anyhow!("invalid {}:\n{}", path.display(), details)
+        })
+    }
+
+    /// Parses and validates the text of a HIPPOFACTS file, reporting every problem found
+    pub fn parse(text: &str) -> Result<Self, Vec<FactsError>> {
+        let raw: RawHippoFacts = toml::from_str(text).map_err(|e| vec![syntax_error(e)])?;
+        Validator::new(text).validate(raw)
+    }
+
+    /// Checks that the module files referenced by local handlers exist under `base_dir`
+    pub fn check_files(&self, base_dir: &Path) -> Vec<FactsError> {
+        self.handlers
+            .iter()
+            .enumerate()
+            .filter_map(|(i, h)| match &h.module {
+                HandlerModule::File(name) if !base_dir.join(name).is_file() => Some(FactsError {
+                    key: format!("handler[{}].name", i),
+                    line: None,
+                    message: format!("module file '{}' does not exist", name),
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn has_external_handlers(&self) -> bool {
+        self.handlers
+            .iter()
+            .any(|h| matches!(h.module, HandlerModule::External(_)))
+    }
+
+    pub fn bindle_id(&self) -> String {
+        format!("{}/{}", self.bindle.name, self.bindle.version)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHippoFacts {
+    bindle: RawBindleSpec,
+    annotations: Option<BTreeMap<String, String>>,
+    #[serde(default)]
+    handler: Vec<RawHandler>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawBindleSpec {
+    name: Spanned<String>,
+    version: Spanned<String>,
+    description: Option<String>,
+    authors: Option<Vec<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawHandler {
+    route: Spanned<String>,
+    name: Option<Spanned<String>>,
+    external: Option<RawExternalRef>,
+    #[serde(default)]
+    files: Vec<Spanned<String>>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct RawExternalRef {
+    bindle_id: Spanned<String>,
+    handler_id: Spanned<String>,
+}
+
+fn syntax_error(e: toml::de::Error) -> FactsError {
+    // toml already names the offending key in the message, but the line is reported separately
+    let message = e.to_string();
+    let message = match message.rsplit_once(" at line ") {
+        Some((m, _)) => m.to_owned(),
+        None => message,
+    };
+    FactsError {
+        key: String::new(),
+        line: e.line_col().map(|(line, _)| line + 1),
+        message,
+    }
+}
+
+struct Validator<'a> {
+    text: &'a str,
+    errors: Vec<FactsError>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            errors: vec![],
+        }
+    }
+
+    fn error<T>(&mut self, key: impl Into<String>, at: &Spanned<T>, message: impl Into<String>) {
+        let line = self.text[..at.start()].matches('\n').count() + 1;
+        self.errors.push(FactsError {
+            key: key.into(),
+            line: Some(line),
+            message: message.into(),
+        });
+    }
+
+    fn validate(mut self, raw: RawHippoFacts) -> Result<HippoFacts, Vec<FactsError>> {
+        let name = raw.bindle.name;
+        if name.get_ref().trim().is_empty() {
+            self.error("bindle.name", &name, "name must not be empty");
+        } else if name.get_ref().chars().any(char::is_whitespace) {
+            self.error("bindle.name", &name, "name must not contain whitespace");
+        }
+
+        let version = match Version::parse(raw.bindle.version.get_ref()) {
+            Ok(v) => Some(v),
+            Err(e) => {
+                self.error(
+                    "bindle.version",
+                    &raw.bindle.version,
+                    format!(
+                        "'{}' is not a valid semantic version: {}",
+                        raw.bindle.version.get_ref(),
+                        e
+                    ),
+                );
+                None
+            }
+        };
+
+        if raw.handler.is_empty() {
+            self.errors.push(FactsError {
+                key: "handler".to_owned(),
+                line: None,
+                message: "at least one [[handler]] must be defined".to_owned(),
+            });
+        }
+
+        let mut routes: HashMap<String, usize> = HashMap::new();
+        let mut handlers = vec![];
+        for (i, h) in raw.handler.into_iter().enumerate() {
+            let key = |field: &str| format!("handler[{}].{}", i, field);
+
+            if !h.route.get_ref().starts_with('/') {
+                self.error(key("route"), &h.route, "route must begin with '/'");
+            }
+            if let Some(other) = routes.insert(h.route.get_ref().clone(), i) {
+                self.error(
+                    key("route"),
+                    &h.route,
+                    format!(
+                        "route '{}' is already used by handler[{}]",
+                        h.route.get_ref(),
+                        other
+                    ),
+                );
+            }
+
+            let module = match (h.name, h.external) {
+                (Some(name), None) => {
+                    if name.get_ref().trim().is_empty() {
+                        self.error(key("name"), &name, "module path must not be empty");
+                    } else if Path::new(name.get_ref()).is_absolute() {
+                        self.error(
+                            key("name"),
+                            &name,
+                            "module path must be relative to HIPPOFACTS",
+                        );
+                    }
+                    Some(HandlerModule::File(name.into_inner()))
+                }
+                (None, Some(ext)) => {
+                    match ext.bindle_id.get_ref().split_once('/') {
+                        Some((n, v)) if !n.is_empty() && Version::parse(v).is_ok() => (),
+                        _ => self.error(
+                            key("external.bindleId"),
+                            &ext.bindle_id,
+                            "bindle ID must be of the form 'name/version'",
+                        ),
+                    }
+                    if ext.handler_id.get_ref().trim().is_empty() {
+                        self.error(
+                            key("external.handlerId"),
+                            &ext.handler_id,
+                            "handler ID must not be empty",
+                        );
+                    }
+                    Some(HandlerModule::External(ExternalRef {
+                        bindle_id: ext.bindle_id.into_inner(),
+                        handler_id: ext.handler_id.into_inner(),
+                    }))
+                }
+                (Some(name), Some(_)) => {
+                    self.error(
+                        key("name"),
+                        &name,
+                        "a handler cannot have both a name and an external reference",
+                    );
+                    None
+                }
+                (None, None) => {
+                    self.error(
+                        key("name"),
+                        &h.route,
+                        "a handler must have either a name or an external reference",
+                    );
+                    None
+                }
+            };
+
+            for (j, file) in h.files.iter().enumerate() {
+                if let Err(e) = glob::Pattern::new(file.get_ref()) {
+                    self.error(
+                        format!("handler[{}].files[{}]", i, j),
+                        file,
+                        format!("'{}' is not a valid glob pattern: {}", file.get_ref(), e),
+                    );
+                } else if Path::new(file.get_ref()).is_absolute() {
+                    self.error(
+                        format!("handler[{}].files[{}]", i, j),
+                        file,
+                        "file patterns must be relative to HIPPOFACTS",
+                    );
+                }
+            }
+
+            if let Some(module) = module {
+                handlers.push(Handler {
+                    route: h.route.into_inner(),
+                    module,
+                    files: h.files.into_iter().map(Spanned::into_inner).collect(),
+                });
+            }
+        }
+
+        match version {
+            Some(version) if self.errors.is_empty() => Ok(HippoFacts {
+                bindle: BindleSpec {
+                    name: name.into_inner(),
+                    version,
+                    description: raw.bindle.description,
+                    authors: raw.bindle.authors,
+                },
+                annotations: raw.annotations,
+                handlers,
+            }),
+            _ => Err(self.errors),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn errors(text: &str) -> Vec<(String, Option<usize>)> {
+        HippoFacts::parse(text)
+            .unwrap_err()
+            .into_iter()
+            .map(|e| (e.key, e.line))
+            .collect()
+    }
+
+    #[test]
+    fn parses_valid_facts() {
+        let facts = HippoFacts::parse(
+            r#"
+[bindle]
+name = "weather"
+version = "1.2.3"
+
+[[handler]]
+route = "/"
+name = "weather.wasm"
+files = ["static/*"]
+
+[[handler]]
+route = "/fs"
+external.bindleId = "fileserver/1.0.0"
+external.handlerId = "fileserver.wasm"
+"#,
+        )
+        .unwrap();
+        assert_eq!(facts.bindle_id(), "weather/1.2.3");
+        assert_eq!(facts.handlers.len(), 2);
+        assert!(facts.has_external_handlers());
+    }
+
+    #[test]
+    fn reports_every_error_with_its_key_and_line() {
+        let text = r#"[bindle]
+name = "weather app"
+version = "one"
+
+[[handler]]
+route = "nope"
+name = "a.wasm"
+
+[[handler]]
+route = "nope"
+"#;
+        assert_eq!(
+            errors(text),
+            vec![
+                ("bindle.name".to_owned(), Some(2)),
+                ("bindle.version".to_owned(), Some(3)),
+                ("handler[0].route".to_owned(), Some(6)),
+                ("handler[1].route".to_owned(), Some(10)),
+                ("handler[1].route".to_owned(), Some(10)),
+                ("handler[1].name".to_owned(), Some(10)),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_external_references_and_files() {
+        let text = r#"[bindle]
+name = "weather"
+version = "1.0.0"
+
+[[handler]]
+route = "/"
+files = ["/etc/passwd", "["]
+external.bindleId = "fileserver"
+external.handlerId = ""
+"#;
+        assert_eq!(
+            errors(text),
+            vec![
+                ("handler[0].external.bindleId".to_owned(), Some(8)),
+                ("handler[0].external.handlerId".to_owned(), Some(9)),
+                ("handler[0].files[0]".to_owned(), Some(7)),
+                ("handler[0].files[1]".to_owned(), Some(7)),
+            ]
+        );
+    }
+
+    #[test]
+    fn requires_a_handler() {
+        assert_eq!(
+            errors("[bindle]\nname = \"weather\"\nversion = \"1.0.0\"\n"),
+            vec![("handler".to_owned(), None)]
+        );
+    }
+
+    #[test]
+    fn reports_the_line_of_syntax_errors() {
+        let errors = HippoFacts::parse("[bindle]\nname = \"weather\"\nversion = \n").unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, Some(3));
+        assert!(!errors[0].message.contains("at line"));
+    }
+}
@@ -2,3 +2,8 @@ mod client;
 
 pub use client::Client;
 pub use client::ConnectionInfo;
+pub use client::ListOptions;
+pub use client::NotFound;
+pub use client::Page;
+pub use client::Unauthorized;
+pub use client::VariableNotSet;
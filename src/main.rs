@@ -1,5 +1,10 @@
+mod bindle;
 mod cli;
 mod client;
+mod dotenv;
+mod hippofacts;
+mod tls;
+mod versioning;
 
 use cli::Cli;
 
@@ -0,0 +1,221 @@
+use chrono::{DateTime, Utc};
+use semver::{Identifier, Version};
+use std::path::Path;
+use std::process::Command;
+
+/// How `hippo push` chooses the version of the bindle and revision it creates
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum VersionStrategy {
+    /// Use the version in HIPPOFACTS unchanged
+    Exact,
+    /// Append a prerelease suffix of the user, a timestamp and the git commit to the HIPPOFACTS version
+    Prerelease,
+    /// Use the next patch version after the latest revision already registered for the app
+    BumpPatch,
+}
+
+/// Appends `<user>.<timestamp>.g<commit>` to the prerelease identifiers of
+/// `version`, so every push from a developer's machine gets a unique,
+/// correctly ordered version. The user and commit are left out when unknown.
+pub fn prerelease(
+    version: &Version,
+    user: Option<&str>,
+    timestamp: DateTime<Utc>,
+    commit: Option<&str>,
+) -> Version {
+    let mut v = version.clone();
+    if let Some(user) = user.map(sanitize_identifier).filter(|u| !u.is_empty()) {
+        v.pre.push(Identifier::AlphaNumeric(user));
+    }
+    v.pre.push(Identifier::Numeric(
+        timestamp
+            .format("%Y%m%d%H%M%S")
+            .to_string()
+            .parse()
+            .unwrap(),
+    ));
+    if let Some(commit) = commit {
+        // the prefix stops an all-digit hash being read as a number with a leading zero
+        v.pre.push(Identifier::AlphaNumeric(format!("g{}", commit)));
+    }
+    v.build.clear();
+    v
+}
+
+/// Returns `version`, or the patch release after the highest of `existing`
+/// with the same major and minor version if that is not older than `version`
+pub fn bump_patch(version: &Version, existing: impl IntoIterator<Item = Version>) -> Version {
+    let latest = existing
+        .into_iter()
+        .filter(|v| v.major == version.major && v.minor == version.minor && v >= version)
+        .max();
+    match latest {
+        Some(mut v) => {
+            v.increment_patch();
+            v
+        }
+        None => version.clone(),
+    }
+}
+
+/// The highest of `existing` that is older than `version`, which is the
+/// revision a channel ran before `version` if it follows the latest release.
+/// Prereleases are passed over unless `version` is itself a prerelease, so
+/// rolling back a release never lands on a development build.
+pub fn previous(version: &Version, existing: impl IntoIterator<Item = Version>) -> Option<Version> {
+    let include_prereleases = !version.pre.is_empty();
+    existing
+        .into_iter()
+        .filter(|v| v < version && (include_prereleases || v.pre.is_empty()))
+        .max()
+}
+
+/// The abbreviated hash of the commit checked out in `dir`, if it is in a git repository
+pub fn git_short_commit(dir: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_owned();
+    Some(commit).filter(|c| !c.is_empty())
+}
+
+fn sanitize_identifier(s: &str) -> String {
+    s.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '-'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(vs: &[&str]) -> Vec<Version> {
+        vs.iter().map(|v| Version::parse(v).unwrap()).collect()
+    }
+
+    fn timestamp() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2022-07-21T09:05:03Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    #[test]
+    fn prerelease_appends_user_timestamp_and_commit() {
+        let version = Version::parse("1.2.0+build.5").unwrap();
+        assert_eq!(
+            prerelease(&version, Some("Alice Smith"), timestamp(), Some("0123abc")).to_string(),
+            "1.2.0-alice-smith.20220721090503.g0123abc"
+        );
+    }
+
+    #[test]
+    fn prerelease_extends_an_existing_prerelease() {
+        let version = Version::parse("1.2.0-rc.1").unwrap();
+        assert_eq!(
+            prerelease(&version, None, timestamp(), None).to_string(),
+            "1.2.0-rc.1.20220721090503"
+        );
+    }
+
+    #[test]
+    fn prerelease_leaves_out_a_user_with_no_usable_characters() {
+        let version = Version::parse("1.2.0").unwrap();
+        assert_eq!(
+            prerelease(&version, Some(""), timestamp(), Some("0123abc")).to_string(),
+            "1.2.0-20220721090503.g0123abc"
+        );
+    }
+
+    #[test]
+    fn prereleases_order_by_timestamp_and_before_the_release() {
+        let version = Version::parse("1.2.0").unwrap();
+        let earlier = prerelease(&version, Some("alice"), timestamp(), Some("fff"));
+        let later = prerelease(
+            &version,
+            Some("alice"),
+            timestamp() + chrono::Duration::seconds(1),
+            Some("000"),
+        );
+        assert!(earlier < later);
+        assert!(later < version);
+    }
+
+    #[test]
+    fn bump_patch_follows_the_latest_matching_version() {
+        let existing = versions(&["1.2.0", "1.2.3", "1.2.1", "1.3.0", "2.0.0"]);
+        assert_eq!(
+            bump_patch(&Version::parse("1.2.0").unwrap(), existing).to_string(),
+            "1.2.4"
+        );
+    }
+
+    #[test]
+    fn bump_patch_keeps_a_version_newer_than_any_existing() {
+        let existing = versions(&["1.2.0", "1.2.3"]);
+        assert_eq!(
+            bump_patch(&Version::parse("1.2.5").unwrap(), existing).to_string(),
+            "1.2.5"
+        );
+        assert_eq!(
+            bump_patch(&Version::parse("1.4.0").unwrap(), vec![]).to_string(),
+            "1.4.0"
+        );
+    }
+
+    #[test]
+    fn previous_is_the_highest_older_version() {
+        let existing = versions(&["1.0.0", "1.1.0", "1.2.0", "2.0.0"]);
+        assert_eq!(
+            previous(&Version::parse("1.2.0").unwrap(), existing),
+            Some(Version::parse("1.1.0").unwrap())
+        );
+    }
+
+    #[test]
+    fn previous_is_none_when_nothing_is_older() {
+        let existing = versions(&["1.0.0", "1.1.0"]);
+        assert_eq!(previous(&Version::parse("1.0.0").unwrap(), existing), None);
+    }
+
+    #[test]
+    fn previous_of_a_release_skips_prereleases() {
+        let existing = versions(&["1.1.0", "1.2.0-alice.20220101000000", "1.2.0"]);
+        assert_eq!(
+            previous(&Version::parse("1.2.0").unwrap(), existing),
+            Some(Version::parse("1.1.0").unwrap())
+        );
+    }
+
+    #[test]
+    fn previous_of_a_release_is_none_when_only_prereleases_are_older() {
+        let existing = versions(&["1.0.0-rc.1", "1.0.0"]);
+        assert_eq!(previous(&Version::parse("1.0.0").unwrap(), existing), None);
+    }
+
+    #[test]
+    fn previous_of_a_prerelease_includes_prereleases() {
+        let existing = versions(&[
+            "1.1.0",
+            "1.2.0-alice.20220101000000",
+            "1.2.0-alice.20220102000000",
+        ]);
+        assert_eq!(
+            previous(
+                &Version::parse("1.2.0-alice.20220102000000").unwrap(),
+                existing
+            ),
+            Some(Version::parse("1.2.0-alice.20220101000000").unwrap())
+        );
+    }
+}